@@ -0,0 +1,209 @@
+//! Builds the breached password filter of the masterpassword web app
+//!
+//! Reads a list of SHA-1 password hashes as published by "Have I Been Pwned" (one `HASH:COUNT`
+//! per line, hexadecimal) and writes a Bloom filter in the format the web app loads.
+//!
+//! Usage: `mpw-breach-filter <hash list> <output file> [false positive rate] [minimum count]`
+//!
+//! The filter size grows with the number of hashes and shrinks with the false positive rate
+//! (default 0.01). A minimum count (default 1) keeps only hashes seen in that many breaches, which
+//! makes the filter considerably smaller.
+
+use std::{
+    env,
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    process,
+};
+
+const MAGIC: &[u8] = b"MPWB";
+
+const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Parse one line of the hash list into the hash digest and its breach count
+fn parse_line(line: &str) -> Option<([u8; 20], u64)> {
+    let (hash, count) = line.trim().split_once(':').unwrap_or((line.trim(), "1"));
+    if hash.len() != 40 {
+        return None;
+    }
+    let mut digest = [0u8; 20];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hash[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some((digest, count.trim().parse().ok()?))
+}
+
+/// Call `f` for every hash of the list seen in at least `min_count` breaches
+fn for_each_hash(
+    path: &str,
+    min_count: u64,
+    mut f: impl FnMut(&[u8; 20]),
+) -> Result<(), Box<dyn Error>> {
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        match parse_line(&line) {
+            Some((digest, count)) if count >= min_count => f(&digest),
+            Some(_) => (),
+            None if line.trim().is_empty() => (),
+            None => return Err(format!("Invalid line '{}'", line).into()),
+        }
+    }
+    Ok(())
+}
+
+/// Bit positions of a hash (must match `BreachFilter::contains` of the web app, which pins the
+/// same positions in its tests)
+fn bit_positions(digest: &[u8; 20], hashes: u32, len: u64) -> impl Iterator<Item = u64> {
+    let h1 = u64::from_le_bytes(digest[..8].try_into().unwrap());
+    let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap()) | 1;
+    (0..hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % len)
+}
+
+/// Bloom filter in the making
+struct Filter {
+    hashes: u32,
+    bits: Vec<u8>,
+}
+
+impl Filter {
+    /// Empty filter sized for `count` hashes and the false positive rate
+    fn new(count: u64, rate: f64) -> Filter {
+        let ln2 = std::f64::consts::LN_2;
+        let bytes = ((-(count as f64) * rate.ln() / (ln2 * ln2)) / 8.0).ceil() as u64;
+        let hashes = ((bytes as f64 * 8.0 / count as f64) * ln2).round().max(1.0) as u32;
+        Filter {
+            hashes,
+            bits: vec![0u8; bytes as usize],
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.bits.len() as u64 * 8
+    }
+
+    fn insert(&mut self, digest: &[u8; 20]) {
+        for bit in bit_positions(digest, self.hashes, self.len()) {
+            self.bits[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+
+    /// The filter in the format the web app loads
+    fn to_bytes(&self) -> Vec<u8> {
+        [MAGIC, &self.hashes.to_le_bytes(), &self.bits].concat()
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (input, output) = match args {
+        [input, output, ..] => (input, output),
+        _ => return Err("Usage: mpw-breach-filter <hash list> <output file> [false positive rate] [minimum count]".into()),
+    };
+    let rate = match args.get(2) {
+        Some(rate) => rate.parse::<f64>()?,
+        None => DEFAULT_FALSE_POSITIVE_RATE,
+    };
+    if !(0.0..1.0).contains(&rate) || rate == 0.0 {
+        return Err(format!("False positive rate {} is not between 0 and 1", rate).into());
+    }
+    let min_count = match args.get(3) {
+        Some(count) => count.parse::<u64>()?,
+        None => 1,
+    };
+
+    // first pass: count the hashes to size the filter
+    let mut count = 0u64;
+    for_each_hash(input, min_count, |_| count += 1)?;
+    if count == 0 {
+        return Err("No hashes to add".into());
+    }
+
+    // second pass: set the bits
+    let mut filter = Filter::new(count, rate);
+    for_each_hash(input, min_count, |digest| filter.insert(digest))?;
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    writer.write_all(&filter.to_bytes())?;
+    writer.flush()?;
+
+    println!(
+        "Wrote {} hashes into a filter of {} KiB with {} hash functions",
+        count,
+        filter.bits.len() / 1024,
+        filter.hashes
+    );
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SHA-1 of "password"
+    const PASSWORD: &str = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8";
+    // SHA-1 of "123456"
+    const NUMBERS: &str = "7C4A8D09CA3762AF61E59520943DC26494F8941B";
+
+    fn digest(hash: &str) -> [u8; 20] {
+        parse_line(hash).unwrap().0
+    }
+
+    /// Lookup as done by `BreachFilter::contains` of the web app
+    fn contains(bytes: &[u8], digest: &[u8; 20]) -> bool {
+        let hashes = u32::from_le_bytes(bytes[MAGIC.len()..MAGIC.len() + 4].try_into().unwrap());
+        let bits = &bytes[MAGIC.len() + 4..];
+        bit_positions(digest, hashes, bits.len() as u64 * 8)
+            .all(|bit| bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+    }
+
+    #[test]
+    fn bit_positions_match_the_web_app() {
+        // the web app's tests expect the same positions
+        let positions = |hash| bit_positions(&digest(hash), 3, 64).collect::<Vec<_>>();
+        assert_eq!(positions(PASSWORD), vec![27, 34, 41]);
+        assert_eq!(positions(NUMBERS), vec![60, 29, 62]);
+    }
+
+    #[test]
+    fn parses_hash_lines() {
+        let (digest, count) = parse_line(&format!("{}:42", PASSWORD)).unwrap();
+        assert_eq!(&digest[..4], &[0x5b, 0xaa, 0x61, 0xe4]);
+        assert_eq!(count, 42);
+        assert_eq!(parse_line(PASSWORD).unwrap().1, 1);
+        assert!(parse_line("5BAA61E4:1").is_none());
+        assert!(parse_line(&format!("{}:many", PASSWORD)).is_none());
+    }
+
+    #[test]
+    fn written_filter_contains_the_added_hashes() {
+        let dir = env::temp_dir().join(format!("mpw-breach-filter-test-{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("hashes.txt");
+        let output = dir.join("breached.bin");
+        std::fs::write(&input, format!("{}:10\n\n{}:1\n", PASSWORD, NUMBERS)).unwrap();
+
+        let path = |p: &std::path::Path| p.to_str().unwrap().to_string();
+        run(&[
+            path(&input),
+            path(&output),
+            "0.01".to_string(),
+            "2".to_string(),
+        ])
+        .unwrap();
+        let bytes = std::fs::read(&output).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(&bytes[..MAGIC.len()], MAGIC);
+        assert!(contains(&bytes, &digest(PASSWORD)));
+        // below the minimum count
+        assert!(!contains(&bytes, &digest(NUMBERS)));
+    }
+}
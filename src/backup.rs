@@ -0,0 +1,120 @@
+use std::error::Error;
+
+use leptos::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{
+    import::ImportFormat,
+    merge::{classify, MergeItem, MergeReview},
+    sites::Site,
+    storage::EncryptedSites,
+    RwLoginData, RwStorage,
+};
+
+const BACKUP_FILE_NAME: &str = "mpw-backup.json";
+
+/// Parse a backup file: either an encrypted backup of the current user or a plain list of sites
+fn parse_backup(content: &str, storage_password: &str) -> Result<Vec<Site>, Box<dyn Error>> {
+    if let Ok(encrypted_sites) = serde_json::from_str::<EncryptedSites>(content) {
+        encrypted_sites.decrypt(storage_password)
+    } else {
+        Ok(serde_json::from_str(content)?)
+    }
+}
+
+#[component]
+pub fn Backup(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // signals
+    let review = create_rw_signal::<Option<Vec<MergeItem>>>(cx, None);
+    let import_invalid = create_rw_signal(cx, false);
+    let import_format = create_rw_signal(cx, "backup".to_string());
+
+    // derived signals
+    let export_href = move || {
+        let backup = store()
+            .encrypted_sites(&login_name())
+            .map(|encrypted_sites| serde_json::to_string(&encrypted_sites).unwrap())
+            .unwrap_or("[]".to_string());
+        format!(
+            "data:application/json;charset=utf-8,{}",
+            js_sys::encode_uri_component(&backup)
+        )
+    };
+
+    let on_import = move |ev: ev::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let file = input.files().and_then(|files| files.get(0));
+        // allow selecting the same file again
+        input.set_value("");
+
+        if let Some(file) = file {
+            spawn_local(async move {
+                let content = JsFuture::from(file.text())
+                    .await
+                    .ok()
+                    .and_then(|content| content.as_string())
+                    .unwrap_or_default();
+
+                let imported = match ImportFormat::parse(&import_format()) {
+                    Some(format) => format.import(&content),
+                    None => parse_backup(&content, &storage_password()),
+                };
+
+                match imported {
+                    Ok(incoming) => {
+                        let mine = store()
+                            .decrypt_sites(&login_name(), &storage_password())
+                            .unwrap_or(Vec::new());
+                        import_invalid.set(false);
+                        review.set(Some(classify(&mine, &incoming)));
+                    }
+                    Err(e) => {
+                        log!("Error importing file: {}", e);
+                        import_invalid.set(true);
+                    }
+                }
+            });
+        }
+    };
+
+    view! { cx,
+        <MergeReview items=review />
+
+        <div class="row justify-content-end mb-3">
+            <div class="col-auto">
+                // Export encrypted backup
+                <a class="btn btn-light btn-outline-secondary" href=export_href download=BACKUP_FILE_NAME>
+                    <i class="fa-solid fa-file-export" />" Export"
+                </a>
+            </div>
+            <div class="col-12 col-md-8 col-lg-5">
+                // Import backup or export of another password manager
+                <div class="input-group">
+                    <span class="input-group-text">
+                        <i class="fa-solid fa-file-import"/>
+                    </span>
+                    <select class="form-select"
+                        on:change=move |ev| import_format.set(event_target_value(&ev))
+                        prop:value=import_format
+                    >
+                        <option value="backup">"MPW Backup"</option>
+                        <option value="bitwarden">"Bitwarden (JSON)"</option>
+                        <option value="keepass">"KeePass (XML)"</option>
+                        <option value="csv">"CSV"</option>
+                    </select>
+                    <input type="file" accept=".json,.xml,.csv"
+                        class=move || if import_invalid() {"form-control is-invalid"} else {"form-control"}
+                        on:change=on_import
+                    />
+                </div>
+            </div>
+        </div>
+    }
+}
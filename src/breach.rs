@@ -0,0 +1,356 @@
+//! Offline check of generated passwords against a breach corpus
+//!
+//! The corpus is a Bloom filter over SHA-1 hashes of breached passwords (e.g. built from the
+//! "Have I Been Pwned" hash list with the `breach-filter` workspace tool). It is loaded from a
+//! local file and kept in the browser's IndexedDB; passwords never leave the browser.
+//!
+//! File format: the magic bytes `MPWB`, the number of hash functions as little endian `u32`, then
+//! the bit array. Bit `i` of the filter is bit `i % 8` of byte `i / 8`.
+
+use std::{error::Error, rc::Rc};
+
+use leptos::*;
+use mpw::masterkey::MasterKey;
+use sha1::{Digest, Sha1};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbRequest, IdbTransactionMode};
+
+use crate::{sites::Site, RwBreachFilter, RwLoginData, RwStorage};
+
+const MAGIC: &[u8] = b"MPWB";
+
+const DB_NAME: &str = "mpw-leptos";
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "breach-filter";
+const FILTER_KEY: &str = "filter";
+
+// counters tried when bumping a breached password
+const MAX_COUNTER_BUMP: i32 = 100;
+
+/// Bloom filter of the SHA-1 hashes of breached passwords
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreachFilter {
+    hashes: u32,
+    bits: Vec<u8>,
+}
+
+impl BreachFilter {
+    pub fn from_bytes(bytes: &[u8]) -> Result<BreachFilter, Box<dyn Error>> {
+        if bytes.len() <= MAGIC.len() + 4 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err("Not a breached password filter".into());
+        }
+        let hashes = u32::from_le_bytes(bytes[MAGIC.len()..MAGIC.len() + 4].try_into()?);
+        if hashes == 0 {
+            return Err("Breached password filter without hash functions".into());
+        }
+        Ok(BreachFilter {
+            hashes,
+            bits: bytes[MAGIC.len() + 4..].to_vec(),
+        })
+    }
+
+    /// Size of the filter in bytes
+    pub fn size(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Whether the password is (probably) in the breach corpus
+    pub fn contains(&self, password: &str) -> bool {
+        let digest = Sha1::digest(password.as_bytes());
+        // double hashing with two 64 bit halves of the digest, as in `bit_positions` of the
+        // breach-filter tool
+        let h1 = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap()) | 1;
+        let len = self.bits.len() as u64 * 8;
+        (0..self.hashes as u64).all(|i| {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % len;
+            self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0
+        })
+    }
+
+    /// Lowest counter from `site.counter + 1` on whose password is not breached
+    pub fn next_safe_counter(&self, masterkey: &MasterKey, site: &Site) -> i32 {
+        let password_type = site.password_type.as_str().into();
+        (site.counter + 1..site.counter + MAX_COUNTER_BUMP)
+            .find(|counter| {
+                !self.contains(&masterkey.generate_password(
+                    &site.site_name,
+                    &password_type,
+                    *counter,
+                ))
+            })
+            .unwrap_or(site.counter + 1)
+    }
+}
+
+/// Wait for an IndexedDB request to finish
+async fn request_result(request: &IdbRequest) -> Result<JsValue, Box<dyn Error>> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let finished = request.clone();
+        let on_success = Closure::once_into_js(move || {
+            let _ = resolve.call1(
+                &JsValue::NULL,
+                &finished.result().unwrap_or(JsValue::UNDEFINED),
+            );
+        });
+        let on_error = Closure::once_into_js(move || {
+            let _ = reject.call0(&JsValue::NULL);
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise)
+        .await
+        .map_err(|e| format!("IndexedDB request failed: {:?}", e).into())
+}
+
+async fn open_database() -> Result<IdbDatabase, Box<dyn Error>> {
+    let factory = window()
+        .indexed_db()
+        .map_err(|e| format!("{:?}", e))?
+        .ok_or("IndexedDB is not available")?;
+    let request = factory
+        .open_with_u32(DB_NAME, DB_VERSION)
+        .map_err(|e| format!("{:?}", e))?;
+
+    let upgraded = request.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        if let Ok(db) = upgraded.result() {
+            let db: IdbDatabase = db.unchecked_into();
+            db.create_object_store(STORE_NAME)
+                .map(|_| ())
+                .unwrap_or_else(|e| log!("Error creating object store: {:?}", e));
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+    Ok(request_result(&request).await?.unchecked_into())
+}
+
+/// Run a request on the filter's object store
+async fn with_store(
+    mode: IdbTransactionMode,
+    f: impl FnOnce(&web_sys::IdbObjectStore) -> Result<IdbRequest, JsValue>,
+) -> Result<JsValue, Box<dyn Error>> {
+    let db = open_database().await?;
+    let store = db
+        .transaction_with_str_and_mode(STORE_NAME, mode)
+        .and_then(|t| t.object_store(STORE_NAME))
+        .map_err(|e| format!("{:?}", e))?;
+    let request = f(&store).map_err(|e| format!("{:?}", e))?;
+    let result = request_result(&request).await;
+    db.close();
+    result
+}
+
+/// Load the filter stored in IndexedDB, if any
+pub async fn load_filter() -> Result<Option<BreachFilter>, Box<dyn Error>> {
+    let value = with_store(IdbTransactionMode::Readonly, |store| {
+        store.get(&JsValue::from_str(FILTER_KEY))
+    })
+    .await?;
+    if value.is_undefined() {
+        return Ok(None);
+    }
+    let bytes = js_sys::Uint8Array::new(&value).to_vec();
+    BreachFilter::from_bytes(&bytes).map(Some)
+}
+
+async fn store_filter(bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let value = js_sys::Uint8Array::from(bytes);
+    with_store(IdbTransactionMode::Readwrite, |store| {
+        store.put_with_key(&value, &JsValue::from_str(FILTER_KEY))
+    })
+    .await
+    .map(|_| ())
+}
+
+async fn delete_filter() -> Result<(), Box<dyn Error>> {
+    with_store(IdbTransactionMode::Readwrite, |store| {
+        store.delete(&JsValue::from_str(FILTER_KEY))
+    })
+    .await
+    .map(|_| ())
+}
+
+/// Sites whose current password is in the breach corpus
+pub fn breached_sites(filter: &BreachFilter, masterkey: &MasterKey, sites: &[Site]) -> Vec<Site> {
+    sites
+        .iter()
+        .filter(|s| {
+            filter.contains(&masterkey.generate_password(
+                &s.site_name,
+                &s.password_type.as_str().into(),
+                s.counter,
+            ))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Loading of the breach filter and the bulk remedy for breached sites
+#[component]
+pub fn BreachCheck(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let masterkey = move || login_data().unwrap().masterkey;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+    let breach_filter = use_context::<RwBreachFilter>(cx).unwrap().0;
+
+    // signals
+    let loading = create_rw_signal(cx, false);
+    let error = create_rw_signal::<Option<String>>(cx, None);
+
+    // derived signals
+    let breached = move || {
+        let sites = store()
+            .decrypt_sites(&login_name(), &storage_password())
+            .unwrap_or(Vec::new());
+        breach_filter()
+            .map(|filter| breached_sites(&filter, &masterkey(), &sites))
+            .unwrap_or_default()
+    };
+
+    let on_load = move |ev: ev::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let file = input.files().and_then(|files| files.get(0));
+        // allow selecting the same file again
+        input.set_value("");
+
+        if let Some(file) = file {
+            loading.set(true);
+            spawn_local(async move {
+                let loaded = async {
+                    let buffer = JsFuture::from(file.array_buffer())
+                        .await
+                        .map_err(|e| format!("Error reading file: {:?}", e))?;
+                    let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+                    let filter = BreachFilter::from_bytes(&bytes)?;
+                    store_filter(&bytes).await?;
+                    Ok::<_, Box<dyn Error>>(filter)
+                };
+                match loaded.await {
+                    Ok(filter) => {
+                        error.set(None);
+                        breach_filter.set(Some(Rc::new(filter)));
+                    }
+                    Err(e) => error.set(Some(e.to_string())),
+                }
+                loading.set(false);
+            });
+        }
+    };
+
+    let on_remove = move |_| {
+        spawn_local(async move {
+            match delete_filter().await {
+                Ok(()) => breach_filter.set(None),
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    let bump_counters = move |_| {
+        if let Some(filter) = breach_filter() {
+            let sites = breached()
+                .into_iter()
+                .map(|site| Site {
+                    counter: filter.next_safe_counter(&masterkey(), &site),
+                    ..site
+                })
+                .collect::<Vec<_>>();
+            store.update(|data| {
+                match data.update_sites(&login_name(), &storage_password(), sites) {
+                    Ok(()) => error.set(None),
+                    Err(e) => error.set(Some(e.to_string())),
+                }
+            });
+        }
+    };
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-header">
+                <span class="fs-4">"Breached Passwords"</span>
+            </div>
+            <div class="card-body text-bg-light">
+                <p class="text-muted">
+                    "Checks the generated passwords against a local filter of breached password hashes. "
+                    "Nothing is sent anywhere."
+                </p>
+                <div class="input-group mb-2">
+                    <span class="input-group-text">
+                        <i class="fa-solid fa-shield-halved"/>
+                    </span>
+                    <input type="file" class="form-control" disabled=loading on:change=on_load />
+                    <button class="btn btn-light btn-outline-danger" type="button"
+                        disabled=move || breach_filter().is_none()
+                        on:click=on_remove
+                    >
+                        <i class="fa-solid fa-trash-can" />" Remove"
+                    </button>
+                </div>
+                {move || match breach_filter() {
+                    Some(filter) => {
+                        let count = breached().len();
+                        view! { cx,
+                            <div class="d-flex justify-content-between align-items-center">
+                                <span>
+                                    {format!("Filter of {} KiB loaded, ", filter.size() / 1024)}
+                                    <span class=if count == 0 {"badge text-bg-success"} else {"badge text-bg-danger"}>{count}</span>
+                                    " breached passwords"
+                                </span>
+                                <button class="btn btn-sm btn-light btn-outline-secondary" type="button"
+                                    title="Increment the counters to the next password that is not breached"
+                                    disabled=count == 0
+                                    on:click=bump_counters
+                                >
+                                    <i class="fa-solid fa-rotate" />" Bump counters"
+                                </button>
+                            </div>
+                        }.into_view(cx)
+                    }
+                    None => view! { cx, <small class="text-muted">"No filter loaded."</small> }.into_view(cx),
+                }}
+                {move || loading().then(|| view! { cx, <div><small>"Loading filter..."</small></div> })}
+                {move || error().map(|e| view! { cx, <div><small class="text-danger">{e}</small></div> })}
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Filter of 64 bits and 3 hash functions with the given bits set
+    fn filter(bits: &[u64]) -> BreachFilter {
+        let mut bytes = [MAGIC, &3u32.to_le_bytes(), &[0u8; 8]].concat();
+        for bit in bits {
+            bytes[MAGIC.len() + 4 + (bit / 8) as usize] |= 1 << (bit % 8);
+        }
+        BreachFilter::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn looks_up_the_bits_set_by_the_filter_builder() {
+        // positions of "password" and "123456" as pinned in the breach-filter tool's tests
+        let breached = filter(&[27, 34, 41]);
+        assert!(breached.contains("password"));
+        assert!(!breached.contains("123456"));
+        assert!(filter(&[27, 29, 34, 41, 60, 62]).contains("123456"));
+        assert!(!filter(&[27, 34]).contains("password"));
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        assert!(BreachFilter::from_bytes(b"MPWB").is_err());
+        assert!(BreachFilter::from_bytes(b"XXXX\x03\0\0\0\0").is_err());
+        assert!(BreachFilter::from_bytes(b"MPWB\0\0\0\0\0").is_err());
+        assert_eq!(filter(&[]).size(), 8);
+    }
+}
@@ -0,0 +1,257 @@
+use leptos::*;
+
+use crate::{sites::Site, storage::EncryptedSites, trash::trash_with_undo, RwLoginData, RwStorage};
+
+const PASSWORD_TYPES: [&str; 8] = [
+    "Maximum", "Long", "Medium", "Short", "Basic", "PIN", "Name", "Phrase",
+];
+
+/// Change applied to all selected sites
+#[derive(Clone, Debug, PartialEq)]
+pub enum BulkEdit {
+    /// rotates the passwords
+    IncrementCounter,
+    SetPasswordType(String),
+    AddTag(String),
+}
+
+impl BulkEdit {
+    fn apply(&self, site: &mut Site) {
+        match self {
+            BulkEdit::IncrementCounter => site.counter += 1,
+            BulkEdit::SetPasswordType(password_type) => site.password_type = password_type.clone(),
+            BulkEdit::AddTag(tag) => {
+                if !site.tags.contains(tag) {
+                    site.tags.push(tag.clone());
+                }
+            }
+        }
+    }
+}
+
+/// The sites whose IDs are selected
+fn selected(sites: Vec<Site>, selection: &[String]) -> Vec<Site> {
+    sites
+        .into_iter()
+        .filter(|s| selection.contains(&s.id))
+        .collect()
+}
+
+/// The selected sites with the edit applied, to be stored with `update_sites`
+fn edited(sites: Vec<Site>, selection: &[String], edit: &BulkEdit) -> Vec<Site> {
+    selected(sites, selection)
+        .into_iter()
+        .map(|mut site| {
+            edit.apply(&mut site);
+            site
+        })
+        .collect()
+}
+
+/// JSON of the encrypted selected sites, in the format of the backup import
+fn export_json(sites: Vec<Site>, selection: &[String], storage_password: &str) -> String {
+    let encrypted = EncryptedSites::from_sites(&selected(sites, selection), storage_password);
+    serde_json::to_string(&encrypted).unwrap()
+}
+
+/// Toolbar applying an action to all selected sites
+///
+/// Every action is stored as one batched update of the site list.
+#[component]
+pub fn BulkActions(
+    cx: Scope,
+    // IDs of the selected sites
+    selection: RwSignal<Vec<String>>,
+    // IDs of the sites currently listed (for selecting all)
+    visible: Signal<Vec<String>>,
+) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // signals
+    let new_tag = create_rw_signal(cx, "".to_string());
+    // the type select always returns to its placeholder after a change
+    let new_type = create_rw_signal(cx, "".to_string());
+    let error = create_rw_signal::<Option<String>>(cx, None);
+
+    // derived signals
+    let sites = move || {
+        store()
+            .decrypt_sites(&login_name(), &storage_password())
+            .unwrap_or(Vec::new())
+    };
+    let nothing_selected = move || selection().is_empty();
+
+    let update_selected = move |edit: BulkEdit| {
+        let sites = edited(sites(), &selection(), &edit);
+        store.update(
+            |data| match data.update_sites(&login_name(), &storage_password(), sites) {
+                Ok(()) => error.set(None),
+                Err(e) => error.set(Some(e.to_string())),
+            },
+        );
+    };
+
+    let delete_selected = move |_| {
+        let site_ids = selected(sites(), &selection())
+            .into_iter()
+            .map(|s| s.id)
+            .collect::<Vec<_>>();
+        let message = format!("Moved {} sites to the trash", site_ids.len());
+        trash_with_undo(cx, site_ids, message);
+        selection.set(Vec::new());
+    };
+
+    let add_tag = move |_| {
+        let tag = new_tag().trim().to_string();
+        if tag.is_empty() {
+            return;
+        }
+        update_selected(BulkEdit::AddTag(tag));
+        new_tag.set("".to_string());
+    };
+
+    let export_href = move || {
+        format!(
+            "data:application/json;charset=utf-8,{}",
+            js_sys::encode_uri_component(&export_json(sites(), &selection(), &storage_password()))
+        )
+    };
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-body text-bg-light">
+                <div class="d-flex flex-wrap gap-2 align-items-center">
+                    <span class="me-2">{move || format!("{} selected", selection().len())}</span>
+                    <button class="btn btn-sm btn-light btn-outline-secondary" type="button"
+                        on:click=move |_| selection.set(visible())
+                    >
+                        "All"
+                    </button>
+                    <button class="btn btn-sm btn-light btn-outline-secondary" type="button"
+                        on:click=move |_| selection.set(Vec::new())
+                    >
+                        "None"
+                    </button>
+
+                    <button class="btn btn-sm btn-light btn-outline-secondary" type="button"
+                        title="Increment the counter to rotate the passwords"
+                        disabled=nothing_selected
+                        on:click=move |_| update_selected(BulkEdit::IncrementCounter)
+                    >
+                        <i class="fa-solid fa-rotate" />" Counter +1"
+                    </button>
+
+                    <select class="form-select form-select-sm w-auto"
+                        disabled=nothing_selected
+                        on:change=move |ev| {
+                            let password_type = event_target_value(&ev);
+                            if !password_type.is_empty() {
+                                update_selected(BulkEdit::SetPasswordType(password_type));
+                            }
+                            new_type.set("".to_string());
+                        }
+                        prop:value=new_type
+                    >
+                        <option value="" selected=true>"Change type..."</option>
+                        {PASSWORD_TYPES.into_iter().map(|t| view! { cx, <option value=t>{t}</option> }).collect::<Vec<_>>()}
+                    </select>
+
+                    <div class="input-group input-group-sm w-auto">
+                        <input class="form-control" type="text" placeholder="Tag"
+                            on:input=move |ev| new_tag.set(event_target_value(&ev))
+                            prop:value=new_tag
+                        />
+                        <button class="btn btn-light btn-outline-secondary" type="button"
+                            disabled=move || nothing_selected() || new_tag().trim().is_empty()
+                            on:click=add_tag
+                        >
+                            <i class="fa-solid fa-tag" />" Add tag"
+                        </button>
+                    </div>
+
+                    <a class="btn btn-sm btn-light btn-outline-secondary"
+                        class:disabled=nothing_selected
+                        href=export_href
+                        download="mpw-selection.json"
+                    >
+                        <i class="fa-solid fa-file-export" />" Export"
+                    </a>
+
+                    <button class="btn btn-sm btn-light btn-outline-danger" type="button"
+                        disabled=nothing_selected
+                        on:click=delete_selected
+                    >
+                        <i class="fa-solid fa-trash-can" />" Delete"
+                    </button>
+                </div>
+                {move || error().map(|e| view! { cx, <div class="text-danger mt-1">{e}</div> })}
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(id: &str, counter: i32, tags: &[&str]) -> Site {
+        Site {
+            id: id.to_string(),
+            site_name: format!("{}.com", id),
+            counter,
+            password_type: "Maximum".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn selection(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn edits_only_the_selected_sites() {
+        let sites = vec![site("a", 1, &[]), site("b", 2, &[]), site("c", 3, &[])];
+        let edited_sites = edited(
+            sites,
+            &selection(&["c", "a", "gone"]),
+            &BulkEdit::IncrementCounter,
+        );
+        let summary = edited_sites
+            .iter()
+            .map(|s| (s.id.as_str(), s.counter))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![("a", 2), ("c", 4)]);
+    }
+
+    #[test]
+    fn changes_types_and_adds_tags_once() {
+        let sites = vec![site("a", 1, &["work"]), site("b", 1, &[])];
+        let all = selection(&["a", "b"]);
+
+        let typed = edited(
+            sites.clone(),
+            &all,
+            &BulkEdit::SetPasswordType("PIN".to_string()),
+        );
+        assert!(typed.iter().all(|s| s.password_type == "PIN"));
+
+        let tagged = edited(sites, &all, &BulkEdit::AddTag("work".to_string()));
+        assert_eq!(tagged[0].tags, vec!["work"]);
+        assert_eq!(tagged[1].tags, vec!["work"]);
+    }
+
+    #[test]
+    fn exports_the_selected_sites_encrypted() {
+        let sites = vec![site("a", 1, &[]), site("b", 2, &[])];
+        let json = export_json(sites.clone(), &selection(&["b"]), "secret");
+
+        let encrypted: EncryptedSites = serde_json::from_str(&json).unwrap();
+        assert_eq!(encrypted.decrypt("secret").unwrap(), vec![sites[1].clone()]);
+    }
+}
@@ -0,0 +1,200 @@
+//! Normalization of pasted URLs to the domain passwords are generated for
+
+use crate::related_domains::normalize_domain;
+
+/// Public suffixes consisting of more than one label
+///
+/// A bundled excerpt of the public suffix list (https://publicsuffix.org). Single-label top level
+/// domains do not need to be listed, since every TLD is treated as a public suffix.
+#[rustfmt::skip]
+const PUBLIC_SUFFIXES: &[&str] = &[
+    // country code second level domains
+    "co.uk", "org.uk", "me.uk", "ltd.uk", "plc.uk", "net.uk", "ac.uk", "gov.uk", "nhs.uk",
+    "com.au", "net.au", "org.au", "edu.au", "gov.au", "id.au",
+    "co.nz", "net.nz", "org.nz", "govt.nz", "ac.nz",
+    "co.jp", "ne.jp", "or.jp", "ac.jp", "go.jp", "gr.jp",
+    "co.kr", "or.kr", "ne.kr",
+    "com.br", "net.br", "org.br", "gov.br",
+    "com.cn", "net.cn", "org.cn", "gov.cn", "edu.cn",
+    "com.hk", "org.hk", "net.hk",
+    "com.tw", "org.tw", "net.tw",
+    "com.sg", "org.sg", "net.sg", "edu.sg",
+    "co.in", "net.in", "org.in", "firm.in", "gen.in", "ind.in",
+    "com.mx", "org.mx", "gob.mx",
+    "com.ar", "com.co", "com.pe", "com.ve",
+    "co.za", "org.za", "gov.za",
+    "com.tr", "org.tr", "net.tr",
+    "co.il", "org.il", "ac.il",
+    "com.ua", "net.ua", "org.ua",
+    "com.pl", "net.pl", "org.pl",
+    "co.at", "or.at", "ac.at",
+    "com.es", "org.es", "nom.es",
+    "com.pt", "org.pt",
+    "co.id", "or.id", "web.id",
+    "com.my", "org.my", "com.ph", "com.vn", "co.th", "in.th",
+    "com.eg", "com.sa", "com.ng", "co.ke",
+    // hosting platforms with independent subdomains
+    "github.io", "gitlab.io", "herokuapp.com", "blogspot.com", "appspot.com",
+    "azurewebsites.net", "cloudfront.net", "netlify.app", "vercel.app", "pages.dev",
+    "workers.dev", "firebaseapp.com", "web.app", "fly.dev", "onrender.com",
+    "s3.amazonaws.com", "myshopify.com", "wordpress.com", "duckdns.org", "dyndns.org",
+];
+
+/// Top level domains recognized in input without a scheme
+#[rustfmt::skip]
+const TOP_LEVEL_DOMAINS: &[&str] = &[
+    // generic
+    "com", "org", "net", "edu", "gov", "mil", "int", "info", "biz", "name", "pro", "mobi", "aero",
+    "coop", "museum", "travel", "jobs", "tel", "asia", "cat", "xxx", "post",
+    "app", "dev", "page", "io", "ai", "co", "me", "tv", "cc", "ly", "gg", "sh", "so", "to", "fm",
+    "xyz", "online", "site", "website", "tech", "store", "shop", "blog", "cloud", "email", "link",
+    "live", "news", "club", "space", "top", "icu", "vip", "work", "world", "today", "life",
+    "global", "digital", "media", "network", "systems", "solutions", "services", "software",
+    "company", "agency", "studio", "design", "social", "games", "bank", "insurance", "health",
+    "google", "amazon", "apple", "microsoft", "berlin", "london", "nyc", "paris", "tokyo", "eu",
+    // country codes
+    "ac", "ad", "ae", "af", "ag", "al", "am", "ao", "aq", "ar", "as", "at", "au", "aw", "ax", "az",
+    "ba", "bb", "bd", "be", "bf", "bg", "bh", "bi", "bj", "bm", "bn", "bo", "br", "bs", "bt", "bw",
+    "by", "bz", "ca", "cd", "cf", "cg", "ch", "ci", "ck", "cl", "cm", "cn", "cr", "cu", "cv", "cw",
+    "cx", "cy", "cz", "de", "dj", "dk", "dm", "do", "dz", "ec", "ee", "eg", "er", "es", "et", "fi",
+    "fj", "fk", "fo", "fr", "ga", "gd", "ge", "gf", "gh", "gi", "gl", "gm", "gn", "gp", "gq", "gr",
+    "gt", "gu", "gw", "gy", "hk", "hn", "hr", "ht", "hu", "id", "ie", "il", "im", "in", "iq", "ir",
+    "is", "it", "je", "jm", "jo", "jp", "ke", "kg", "kh", "ki", "km", "kn", "kp", "kr", "kw", "ky",
+    "kz", "la", "lb", "lc", "li", "lk", "lr", "ls", "lt", "lu", "lv", "ma", "mc", "md", "mg", "mh",
+    "mk", "ml", "mm", "mn", "mo", "mp", "mq", "mr", "ms", "mt", "mu", "mv", "mw", "mx", "my", "mz",
+    "na", "nc", "ne", "nf", "ng", "ni", "nl", "no", "np", "nr", "nu", "nz", "om", "pa", "pe", "pf",
+    "pg", "ph", "pk", "pl", "pm", "pn", "pr", "ps", "pt", "pw", "py", "qa", "re", "ro", "rs", "ru",
+    "rw", "sa", "sb", "sc", "sd", "se", "sg", "si", "sk", "sl", "sm", "sn", "sr", "ss", "st", "su",
+    "sv", "sx", "sy", "sz", "tc", "td", "tf", "tg", "th", "tj", "tk", "tl", "tm", "tn", "tr", "tt",
+    "tw", "tz", "ua", "ug", "uk", "us", "uy", "uz", "va", "vc", "ve", "vg", "vi", "vn", "vu", "wf",
+    "ws", "ye", "yt", "za", "zm", "zw",
+];
+
+/// Registrable domain of a host name (the public suffix plus one more label)
+///
+/// IP addresses and single-label hosts (e.g. "localhost") are returned unchanged.
+pub fn registrable_domain(host: &str) -> String {
+    let host = normalize_domain(host);
+    let is_ip = host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[');
+    if is_ip || !host.contains('.') {
+        return host;
+    }
+
+    let labels = host.split('.').collect::<Vec<_>>();
+    let suffix_labels = PUBLIC_SUFFIXES
+        .iter()
+        .filter(|suffix| host.ends_with(&format!(".{}", suffix)))
+        .map(|suffix| suffix.split('.').count())
+        .max()
+        .unwrap_or(1);
+
+    let start = labels.len().saturating_sub(suffix_labels + 1);
+    labels[start..].join(".")
+}
+
+/// Whether user input looks like a URL or a domain rather than a search term
+///
+/// Input without a scheme only counts as a domain if it ends in a known top level domain or public
+/// suffix (or is an IP address), so that e.g. "john.doe" or "v1.2" stay search terms.
+pub fn looks_like_url(input: &str) -> bool {
+    let input = input.trim();
+    if input.is_empty() || input.contains(char::is_whitespace) {
+        return false;
+    }
+    if input.contains("://") {
+        return host(input).is_some();
+    }
+
+    let Some(host) = host(input).map(str::to_lowercase) else {
+        return false;
+    };
+    let Some((_, tld)) = host.rsplit_once('.') else {
+        return false;
+    };
+    host.parse::<std::net::Ipv4Addr>().is_ok()
+        || TOP_LEVEL_DOMAINS.contains(&tld)
+        || PUBLIC_SUFFIXES
+            .iter()
+            .any(|suffix| host.ends_with(&format!(".{}", suffix)))
+}
+
+/// Host name of a URL (or bare domain), without scheme, user info, port and path
+fn host(input: &str) -> Option<&str> {
+    let rest = input.split_once("://").map_or(input, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.find(']') {
+        // IPv6 address
+        Some(end) if host.starts_with('[') => &host[..=end],
+        _ => host.split(':').next().unwrap_or_default(),
+    };
+    let valid = !host.is_empty()
+        && (host.starts_with('[')
+            || host
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.')));
+    valid.then_some(host)
+}
+
+/// Registrable domain of a pasted URL (or bare domain)
+pub fn domain_from_url(input: &str) -> Option<String> {
+    let input = input.trim();
+    if !looks_like_url(input) {
+        return None;
+    }
+    host(input).map(registrable_domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registrable_domain_respects_public_suffixes() {
+        assert_eq!(registrable_domain("www.login.example.com"), "example.com");
+        assert_eq!(registrable_domain("shop.example.co.uk"), "example.co.uk");
+        assert_eq!(registrable_domain("me.github.io"), "me.github.io");
+        assert_eq!(registrable_domain("localhost"), "localhost");
+        assert_eq!(registrable_domain("192.168.1.1"), "192.168.1.1");
+    }
+
+    #[test]
+    fn urls_and_domains_are_recognized() {
+        assert!(looks_like_url("https://example.com"));
+        assert!(looks_like_url("http://localhost:8080/admin"));
+        assert!(looks_like_url("example.com"));
+        assert!(looks_like_url("Login.Example.DE/path?q=1"));
+        assert!(looks_like_url("user.github.io"));
+        assert!(looks_like_url("10.0.0.1"));
+    }
+
+    #[test]
+    fn search_terms_are_not_urls() {
+        assert!(!looks_like_url(""));
+        assert!(!looks_like_url("example"));
+        assert!(!looks_like_url("john.doe"));
+        assert!(!looks_like_url("v1.2"));
+        assert!(!looks_like_url("example.com."));
+        assert!(!looks_like_url("example .com"));
+        assert!(!looks_like_url("https://"));
+    }
+
+    #[test]
+    fn domain_from_url_strips_everything_but_the_registrable_domain() {
+        assert_eq!(
+            domain_from_url("https://user:pw@accounts.example.co.uk:8443/login#top"),
+            Some("example.co.uk".to_string())
+        );
+        assert_eq!(
+            domain_from_url(" WWW.Example.com "),
+            Some("example.com".to_string())
+        );
+        assert_eq!(
+            domain_from_url("http://[::1]:8080/"),
+            Some("[::1]".to_string())
+        );
+        assert_eq!(domain_from_url("john.doe"), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Strength of the masterpassword templates (password types)
+//!
+//! The tables mirror the templates and character classes of the masterpassword algorithm
+//! (version 3). Every template character stands for a character class the generated character is
+//! picked from.
+
+use leptos::*;
+
+/// Templates of each password type
+pub const TEMPLATES: [(&str, &[&str]); 8] = [
+    ("Maximum", &["anoxxxxxxxxxxxxxxxxx", "axxxxxxxxxxxxxxxxxno"]),
+    (
+        "Long",
+        &[
+            "CvcvnoCvcvCvcv",
+            "CvcvCvcvnoCvcv",
+            "CvcvCvcvCvcvno",
+            "CvccnoCvcvCvcv",
+            "CvccCvcvnoCvcv",
+            "CvccCvcvCvcvno",
+            "CvcvnoCvccCvcv",
+            "CvcvCvccnoCvcv",
+            "CvcvCvccCvcvno",
+            "CvcvnoCvcvCvcc",
+            "CvcvCvcvnoCvcc",
+            "CvcvCvcvCvccno",
+            "CvccnoCvccCvcv",
+            "CvccCvccnoCvcv",
+            "CvccCvccCvcvno",
+            "CvcvnoCvccCvcc",
+            "CvcvCvccnoCvcc",
+            "CvcvCvccCvccno",
+            "CvccnoCvcvCvcc",
+            "CvccCvcvnoCvcc",
+            "CvccCvcvCvccno",
+        ],
+    ),
+    ("Medium", &["CvcnoCvc", "CvcCvcno"]),
+    ("Short", &["Cvcn"]),
+    ("Basic", &["aaanaaan", "aannaaan", "aaannaaa"]),
+    ("PIN", &["nnnn"]),
+    ("Name", &["cvccvcvcv"]),
+    (
+        "Phrase",
+        &[
+            "cvcc cvc cvccvcv cvc",
+            "cvc cvccvcvcv cvcv",
+            "cv cvccv cvc cvcvccv",
+        ],
+    ),
+];
+
+/// Characters a template character may stand for
+fn character_class(template_char: char) -> &'static str {
+    match template_char {
+        'V' => "AEIOU",
+        'C' => "BCDFGHJKLMNPQRSTVWXYZ",
+        'v' => "aeiou",
+        'c' => "bcdfghjklmnpqrstvwxyz",
+        'A' => "AEIOUBCDFGHJKLMNPQRSTVWXYZ",
+        'a' => "AEIOUaeiouBCDFGHJKLMNPQRSTVWXYZbcdfghjklmnpqrstvwxyz",
+        'n' => "0123456789",
+        'o' => "@&%?,=[]_:-+*$#!'^~;()/.",
+        'x' => "AEIOUaeiouBCDFGHJKLMNPQRSTVWXYZbcdfghjklmnpqrstvwxyz0123456789!@#$%^&*()",
+        _ => " ",
+    }
+}
+
+/// Length, character classes and entropy of a password type
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemplateStrength {
+    pub name: &'static str,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// human readable classes of characters that may occur
+    pub classes: Vec<&'static str>,
+    /// entropy in bits
+    pub bits: f64,
+}
+
+impl TemplateStrength {
+    pub fn of(password_type: &str) -> Option<TemplateStrength> {
+        TEMPLATES
+            .iter()
+            .find(|(name, _)| *name == password_type)
+            .map(|(name, templates)| TemplateStrength::compute(name, templates))
+    }
+
+    pub fn all() -> Vec<TemplateStrength> {
+        TEMPLATES
+            .iter()
+            .map(|(name, templates)| TemplateStrength::compute(name, templates))
+            .collect()
+    }
+
+    fn compute(name: &'static str, templates: &[&str]) -> TemplateStrength {
+        let lengths = templates.iter().map(|t| t.chars().count());
+
+        // one of the templates is picked uniformly, then every character from its class
+        let template_bits = templates
+            .iter()
+            .map(|t| {
+                t.chars()
+                    .map(|c| (character_class(c).chars().count() as f64).log2())
+                    .sum::<f64>()
+            })
+            .sum::<f64>()
+            / templates.len() as f64;
+        let bits = (templates.len() as f64).log2() + template_bits;
+
+        let characters = templates
+            .iter()
+            .flat_map(|t| t.chars().flat_map(|c| character_class(c).chars()))
+            .collect::<Vec<_>>();
+        let mut classes = Vec::new();
+        if characters.iter().any(|c| c.is_ascii_uppercase()) {
+            classes.push("uppercase");
+        }
+        if characters.iter().any(|c| c.is_ascii_lowercase()) {
+            classes.push("lowercase");
+        }
+        if characters.iter().any(|c| c.is_ascii_digit()) {
+            classes.push("digits");
+        }
+        if characters.iter().any(|c| c.is_ascii_punctuation()) {
+            classes.push("symbols");
+        }
+        if characters.contains(&' ') {
+            classes.push("spaces");
+        }
+
+        TemplateStrength {
+            name,
+            min_length: lengths.clone().min().unwrap_or_default(),
+            max_length: lengths.max().unwrap_or_default(),
+            classes,
+            bits,
+        }
+    }
+
+    pub fn length(&self) -> String {
+        if self.min_length == self.max_length {
+            self.min_length.to_string()
+        } else {
+            format!("{}-{}", self.min_length, self.max_length)
+        }
+    }
+
+    /// Rating of the entropy with a matching bootstrap color
+    pub fn rating(&self) -> (&'static str, &'static str) {
+        match self.bits {
+            b if b < 28.0 => ("very weak", "danger"),
+            b if b < 50.0 => ("weak", "warning"),
+            b if b < 70.0 => ("fair", "info"),
+            b if b < 100.0 => ("strong", "success"),
+            _ => ("very strong", "success"),
+        }
+    }
+}
+
+/// Strength of the chosen password type with a comparison of all types
+#[component]
+pub fn TemplateStrengthInfo(cx: Scope, password_type: RwSignal<String>) -> impl IntoView {
+    let strength = move || TemplateStrength::of(&password_type());
+
+    view! { cx,
+        {move || strength().map(|s| {
+            let (rating, color) = s.rating();
+            view! { cx,
+                <small class="text-muted">
+                    {format!("{} characters, {}, {:.0} bits ", s.length(), s.classes.join(", "), s.bits)}
+                    <span class=format!("badge text-bg-{}", color)>{rating}</span>
+                </small>
+            }
+        })}
+        <details>
+            <summary><small class="text-muted">"Compare all types"</small></summary>
+            <table class="table table-sm mb-0">
+                <thead>
+                    <tr><th>"Type"</th><th>"Length"</th><th>"Characters"</th><th>"Bits"</th></tr>
+                </thead>
+                <tbody>
+                    {TemplateStrength::all().into_iter().map(|s| {
+                        let name = s.name;
+                        view! { cx,
+                            <tr class=move || if password_type() == name {"table-active"} else {""}>
+                                <td>{s.name}</td>
+                                <td>{s.length()}</td>
+                                <td>{s.classes.join(", ")}</td>
+                                <td>{format!("{:.0}", s.bits)}</td>
+                            </tr>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+                </tbody>
+            </table>
+        </details>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_bits(password_type: &str, expected: f64) {
+        let bits = TemplateStrength::of(password_type).unwrap().bits;
+        assert!(
+            (bits - expected).abs() < 1e-9,
+            "{}: {}",
+            password_type,
+            bits
+        );
+    }
+
+    #[test]
+    fn bits_of_single_template_types() {
+        assert_bits("PIN", 4.0 * 10f64.log2());
+        assert_bits("Short", 2.0 * 21f64.log2() + 5f64.log2() + 10f64.log2());
+    }
+
+    #[test]
+    fn bits_include_the_choice_of_template() {
+        // both templates have the same classes, just in a different order
+        let medium = 1.0 + 4.0 * 21f64.log2() + 2.0 * 5f64.log2() + 10f64.log2() + 24f64.log2();
+        assert_bits("Medium", medium);
+    }
+
+    #[test]
+    fn lengths_classes_and_ratings() {
+        let pin = TemplateStrength::of("PIN").unwrap();
+        assert_eq!(
+            (pin.length(), pin.classes.clone()),
+            ("4".to_string(), vec!["digits"])
+        );
+        assert_eq!(pin.rating().0, "very weak");
+
+        let maximum = TemplateStrength::of("Maximum").unwrap();
+        assert_eq!(maximum.length(), "20");
+        assert_eq!(
+            maximum.classes,
+            vec!["uppercase", "lowercase", "digits", "symbols"]
+        );
+        assert_eq!(maximum.rating().0, "very strong");
+
+        let phrase = TemplateStrength::of("Phrase").unwrap();
+        assert_eq!(phrase.length(), "18-20");
+        assert!(phrase.classes.contains(&"spaces"));
+
+        assert!(TemplateStrength::of("Unknown").is_none());
+        assert_eq!(TemplateStrength::all().len(), TEMPLATES.len());
+    }
+}
@@ -0,0 +1,151 @@
+//! Hierarchical groups of sites, e.g. "Work/Infrastructure"
+
+use std::collections::BTreeMap;
+
+use crate::sites::Site;
+
+pub const SEPARATOR: char = '/';
+
+fn segments(group: &str) -> Vec<String> {
+    group
+        .split(SEPARATOR)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Group path without surrounding whitespace and empty segments
+pub fn normalize_group(group: &str) -> String {
+    segments(group).join(&SEPARATOR.to_string())
+}
+
+/// Whether `group` is `parent` or one of its subgroups (case-insensitive)
+pub fn is_in_group(group: &str, parent: &str) -> bool {
+    let group = segments(&group.to_lowercase());
+    let parent = segments(&parent.to_lowercase());
+    group.starts_with(&parent)
+}
+
+/// A group with the sites directly inside it
+pub struct GroupSection {
+    pub path: String,
+    /// number of parent groups
+    pub depth: usize,
+    pub name: String,
+    pub sites: Vec<Site>,
+    /// number of sites in this group and all of its subgroups
+    pub total: usize,
+}
+
+/// Sites organized into group sections, parents before their subgroups
+///
+/// Ungrouped sites come first (in a section with an empty path). Parent groups get a section
+/// even if they only contain subgroups. The order of sites within a section is kept.
+pub fn group_sections(sites: Vec<Site>) -> Vec<GroupSection> {
+    let mut groups: BTreeMap<Vec<String>, Vec<Site>> = BTreeMap::new();
+    for site in sites {
+        let path = segments(&site.group);
+        for depth in 1..path.len() {
+            groups.entry(path[..depth].to_vec()).or_default();
+        }
+        groups.entry(path).or_default().push(site);
+    }
+
+    let totals = groups
+        .keys()
+        .map(|path| {
+            groups
+                .iter()
+                // ungrouped sites are no parent of the groups
+                .filter(|(other, _)| {
+                    *other == path || (!path.is_empty() && other.starts_with(path))
+                })
+                .map(|(_, sites)| sites.len())
+                .sum::<usize>()
+        })
+        .collect::<Vec<_>>();
+
+    groups
+        .into_iter()
+        .zip(totals)
+        .map(|((path, sites), total)| GroupSection {
+            depth: path.len().saturating_sub(1),
+            name: path.last().cloned().unwrap_or_default(),
+            path: path.join(&SEPARATOR.to_string()),
+            sites,
+            total,
+        })
+        .collect()
+}
+
+/// All groups in use, including parents of nested groups
+pub fn all_groups(sites: &[Site]) -> Vec<String> {
+    group_sections(sites.to_vec())
+        .into_iter()
+        .map(|section| section.path)
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_are_normalized_and_nested() {
+        assert_eq!(normalize_group(" Work // Infra/ "), "Work/Infra");
+        assert!(is_in_group("Work/Infra", "work"));
+        assert!(is_in_group("Work/Infra", "Work/Infra"));
+        assert!(!is_in_group("Work", "Work/Infra"));
+        // segments are compared as a whole
+        assert!(!is_in_group("Workshop", "Work"));
+    }
+
+    #[test]
+    fn sections_list_parents_before_subgroups() {
+        let site = |name: &str, group: &str| Site {
+            site_name: name.to_string(),
+            group: group.to_string(),
+            ..Default::default()
+        };
+        let sites = vec![
+            site("a", "Work/Infra"),
+            site("b", ""),
+            site("c", "Private"),
+            site("d", "Work/Infra"),
+            site("e", "Work/HR/Payroll"),
+        ];
+
+        let sections = group_sections(sites.clone())
+            .into_iter()
+            .map(|s| {
+                let names = s.sites.iter().map(|s| s.site_name.clone()).collect();
+                (s.path, s.depth, s.total, names)
+            })
+            .collect::<Vec<(String, usize, usize, Vec<String>)>>();
+        let expected = [
+            ("", 0, 1, vec!["b"]),
+            ("Private", 0, 1, vec!["c"]),
+            ("Work", 0, 3, vec![]),
+            ("Work/HR", 1, 1, vec![]),
+            ("Work/HR/Payroll", 2, 1, vec!["e"]),
+            ("Work/Infra", 1, 2, vec!["a", "d"]),
+        ]
+        .map(|(path, depth, total, names)| {
+            let names = names.into_iter().map(str::to_string).collect();
+            (path.to_string(), depth, total, names)
+        });
+        assert_eq!(sections, expected);
+
+        assert_eq!(
+            all_groups(&sites),
+            vec![
+                "Private",
+                "Work",
+                "Work/HR",
+                "Work/HR/Payroll",
+                "Work/Infra"
+            ]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use leptos::*;
+use mpw::masterkey::MasterKey;
+use serde::{Deserialize, Serialize};
+
+use crate::{sites::Site, storage::format_timestamp, RwLoginData};
+
+/// Version of the masterpassword algorithm passwords are generated with
+pub const ALGORITHM_VERSION: u32 = 3;
+
+// number of previous configurations kept per site
+const MAX_HISTORY: usize = 20;
+
+// how long a previous password stays revealed
+const REVEAL_SECONDS: u64 = 15;
+
+/// A previous configuration of a site, i.e. what its password was generated from
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub site_name: String,
+    pub counter: i32,
+    pub password_type: String,
+    pub algorithm: u32,
+    /// milliseconds since the epoch when the configuration was replaced
+    pub timestamp: u64,
+}
+
+impl HistoryEntry {
+    /// The password of this configuration (`None` if its algorithm version is not supported)
+    fn generate_password(&self, masterkey: &MasterKey) -> Option<String> {
+        if self.algorithm != ALGORITHM_VERSION {
+            return None;
+        }
+        Some(masterkey.generate_password(
+            &self.site_name,
+            &self.password_type.as_str().into(),
+            self.counter,
+        ))
+    }
+}
+
+/// History of a site extended by its current configuration (which is about to be replaced)
+pub fn with_previous_configuration(site: &Site, replaced_at: u64) -> Vec<HistoryEntry> {
+    let mut history = site.history.clone();
+    history.push(HistoryEntry {
+        site_name: site.site_name.clone(),
+        counter: site.counter,
+        password_type: site.password_type.clone(),
+        algorithm: ALGORITHM_VERSION,
+        timestamp: replaced_at,
+    });
+    let excess = history.len().saturating_sub(MAX_HISTORY);
+    history.drain(..excess);
+    history
+}
+
+/// List of a site's previous configurations with temporarily revealable passwords
+#[component]
+pub fn PasswordHistory(cx: Scope, history: Vec<HistoryEntry>) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let masterkey = move || login_data().unwrap().masterkey;
+
+    // index of the currently revealed entry
+    let revealed = create_rw_signal::<Option<usize>>(cx, None);
+
+    let reveal = move |idx: usize| {
+        revealed.set(Some(idx));
+        set_timeout(
+            move || {
+                if revealed() == Some(idx) {
+                    revealed.set(None);
+                }
+            },
+            Duration::from_secs(REVEAL_SECONDS),
+        );
+    };
+
+    view! { cx,
+        <ul class="list-group">
+            {history.into_iter().enumerate().rev().map(|(idx, entry)| {
+                let password = entry.generate_password(&masterkey());
+                let supported = password.is_some();
+                view! { cx,
+                    <li class="list-group-item">
+                        <div class="d-flex justify-content-between align-items-center">
+                            <small>
+                                {format!("{}, counter {}", entry.password_type, entry.counter)}
+                                <span class="text-muted ms-2">{entry.site_name.clone()}</span>
+                                <br />
+                                <span class="text-muted">"Replaced "{format_timestamp(entry.timestamp)}</span>
+                            </small>
+                            <button class="btn btn-sm btn-light btn-outline-secondary" type="button"
+                                title="Reveal the previous password"
+                                disabled=!supported
+                                on:click=move |_| {
+                                    if revealed() == Some(idx) {
+                                        revealed.set(None);
+                                    } else {
+                                        reveal(idx);
+                                    }
+                                }
+                            >
+                                <i class=move || if revealed() == Some(idx) {"fa-solid fa-eye-slash"} else {"fa-solid fa-eye"} />
+                            </button>
+                        </div>
+                        {if supported {
+                            view! { cx,
+                                <Show when=move || revealed() == Some(idx) fallback=|_| ()>
+                                    <div class="font-monospace mt-1">{password.clone()}</div>
+                                </Show>
+                            }.into_view(cx)
+                        } else {
+                            view! { cx,
+                                <small class="text-danger">
+                                    {format!("Generated with unsupported algorithm version {}", entry.algorithm)}
+                                </small>
+                            }.into_view(cx)
+                        }}
+                    </li>
+                }
+            })
+            .collect::<Vec<_>>()}
+        </ul>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(counter: i32, history: Vec<HistoryEntry>) -> Site {
+        Site {
+            site_name: "example.com".to_string(),
+            counter,
+            password_type: "Long".to_string(),
+            history,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn appends_the_replaced_configuration() {
+        let first = with_previous_configuration(&site(1, Vec::new()), 10);
+        let second = with_previous_configuration(&site(2, first.clone()), 20);
+        assert_eq!(second.len(), 2);
+        assert_eq!(second[0], first[0]);
+        assert_eq!(
+            second[1],
+            HistoryEntry {
+                site_name: "example.com".to_string(),
+                counter: 2,
+                password_type: "Long".to_string(),
+                algorithm: ALGORITHM_VERSION,
+                timestamp: 20,
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_only_the_latest_configurations() {
+        let history = (1..=MAX_HISTORY as i32).fold(Vec::new(), |history, counter| {
+            with_previous_configuration(&site(counter, history), counter as u64)
+        });
+        assert_eq!(history.len(), MAX_HISTORY);
+
+        let trimmed = with_previous_configuration(&site(100, history), 100);
+        assert_eq!(trimmed.len(), MAX_HISTORY);
+        assert_eq!(trimmed[0].counter, 2);
+        assert_eq!(trimmed[MAX_HISTORY - 1].counter, 100);
+    }
+
+    #[test]
+    fn regenerates_passwords_of_supported_algorithms() {
+        let masterkey = MasterKey::new_auth("Jane Doe", "master password");
+        let mut entry = with_previous_configuration(&site(3, Vec::new()), 1).remove(0);
+        assert_eq!(
+            entry.generate_password(&masterkey),
+            Some(masterkey.generate_password("example.com", &"Long".into(), 3))
+        );
+
+        entry.algorithm = ALGORITHM_VERSION - 1;
+        assert_eq!(entry.generate_password(&masterkey), None);
+    }
+}
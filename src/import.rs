@@ -0,0 +1,289 @@
+use std::{collections::HashMap, error::Error};
+
+use serde::Deserialize;
+use web_sys::{DomParser, Element, SupportedType};
+
+use crate::{domain::domain_from_url, migration::MigrationStatus, sites::Site, storage::timestamp};
+
+/// Export formats of other password managers that can be imported
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImportFormat {
+    Bitwarden,
+    KeePass,
+    Csv,
+}
+
+impl ImportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "bitwarden" => Some(ImportFormat::Bitwarden),
+            "keepass" => Some(ImportFormat::KeePass),
+            "csv" => Some(ImportFormat::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn import(&self, content: &str) -> Result<Vec<Site>, Box<dyn Error>> {
+        match self {
+            ImportFormat::Bitwarden => from_bitwarden(content),
+            ImportFormat::KeePass => from_keepass(content),
+            ImportFormat::Csv => from_csv(content),
+        }
+    }
+}
+
+/// Create a site (without taking over any password) from an imported entry
+///
+/// The site name is derived from the URL's registrable domain if there is one, since that is what
+/// passwords are usually generated for, otherwise the entry's name is used.
+fn imported_site(name: &str, login_name: &str, url: &str, notes: &str) -> Option<Site> {
+    let site_name = domain_from_url(url).unwrap_or(name.trim().to_string());
+    if site_name.is_empty() {
+        return None;
+    }
+    Some(Site {
+        site_name,
+        counter: 1,
+        password_type: "Maximum".to_string(),
+        modified: timestamp(),
+        login_name: login_name.trim().to_string(),
+        url: url.trim().to_string(),
+        notes: notes.trim().to_string(),
+        migration: Some(MigrationStatus::Pending),
+        ..Site::default()
+    })
+}
+
+#[derive(Deserialize)]
+struct BitwardenExport {
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenItem {
+    name: String,
+    notes: Option<String>,
+    login: Option<BitwardenLogin>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenLogin {
+    username: Option<String>,
+    uris: Option<Vec<BitwardenUri>>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+/// Import the login items of an (unencrypted) Bitwarden JSON export
+pub fn from_bitwarden(content: &str) -> Result<Vec<Site>, Box<dyn Error>> {
+    let export: BitwardenExport = serde_json::from_str(content)?;
+
+    Ok(export
+        .items
+        .into_iter()
+        .filter_map(|item| {
+            // only login items refer to accounts on sites
+            let login = item.login?;
+            let url = login
+                .uris
+                .unwrap_or_default()
+                .into_iter()
+                .find_map(|u| u.uri)
+                .unwrap_or_default();
+            imported_site(
+                &item.name,
+                &login.username.unwrap_or_default(),
+                &url,
+                &item.notes.unwrap_or_default(),
+            )
+        })
+        .collect())
+}
+
+/// Text content of the first child element of `element` with the given tag name
+fn child_text(element: &Element, tag: &str) -> String {
+    element
+        .get_elements_by_tag_name(tag)
+        .item(0)
+        .and_then(|e| e.text_content())
+        .unwrap_or_default()
+}
+
+/// Import the entries of a KeePass 2 XML export
+pub fn from_keepass(content: &str) -> Result<Vec<Site>, Box<dyn Error>> {
+    let document = DomParser::new()
+        .and_then(|parser| parser.parse_from_string(content, SupportedType::TextXml))
+        .map_err(|e| format!("XML parser error: {:?}", e))?;
+    if document.get_elements_by_tag_name("parsererror").length() > 0 {
+        return Err("Invalid KeePass XML file".into());
+    }
+
+    let entries = document.get_elements_by_tag_name("Entry");
+    let mut sites = Vec::new();
+    for idx in 0..entries.length() {
+        let Some(entry) = entries.item(idx) else { continue };
+        // skip old versions of entries kept in the entry history
+        let in_history = entry
+            .parent_element()
+            .map(|p| p.tag_name() == "History")
+            .unwrap_or(false);
+        if in_history {
+            continue;
+        }
+
+        let mut fields = HashMap::new();
+        let strings = entry.children();
+        for string_idx in 0..strings.length() {
+            let Some(string) = strings.item(string_idx) else { continue };
+            if string.tag_name() == "String" {
+                fields.insert(child_text(&string, "Key"), child_text(&string, "Value"));
+            }
+        }
+        let field = |key: &str| fields.get(key).cloned().unwrap_or_default();
+
+        sites.extend(imported_site(
+            &field("Title"),
+            &field("UserName"),
+            &field("URL"),
+            &field("Notes"),
+        ));
+    }
+
+    Ok(sites)
+}
+
+/// Split CSV content into records of fields (RFC 4180 quoting)
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+        .into_iter()
+        .filter(|r| r.iter().any(|f| !f.trim().is_empty()))
+        .collect()
+}
+
+/// Import a CSV export with a header line (as written by Bitwarden, LastPass, Chrome, Firefox, ...)
+pub fn from_csv(content: &str) -> Result<Vec<Site>, Box<dyn Error>> {
+    let mut records = parse_csv(content).into_iter();
+    let header = records.next().ok_or("Empty CSV file")?;
+    let header = header
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect::<Vec<_>>();
+
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let name_col = column(&["name", "title", "account"]);
+    let login_col = column(&["username", "login_username", "login", "user", "email"]);
+    let url_col = column(&["url", "login_uri", "uri", "website", "web site"]);
+    let notes_col = column(&["notes", "note", "extra", "comments"]);
+    if name_col.is_none() && url_col.is_none() {
+        return Err("CSV file has neither a name nor a URL column".into());
+    }
+
+    Ok(records
+        .filter_map(|record| {
+            let field =
+                |col: Option<usize>| col.and_then(|c| record.get(c)).cloned().unwrap_or_default();
+            imported_site(
+                &field(name_col),
+                &field(login_col),
+                &field(url_col),
+                &field(notes_col),
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_csv_handles_quotes_and_line_endings() {
+        let records = parse_csv("name,notes\r\n\"Doe, John\",\"say \"\"hi\"\"\nbye\"\r\n\n,\n");
+        assert_eq!(
+            records,
+            vec![
+                vec!["name".to_string(), "notes".to_string()],
+                vec!["Doe, John".to_string(), "say \"hi\"\nbye".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn from_csv_uses_registrable_domain_of_url() {
+        let csv = "name,url,username,password,extra\n\
+                   My Bank,https://login.bank.co.uk/start,jane,secret,main account\n\
+                   Router,,admin,admin,\n\
+                   ,,,,\n";
+        let sites = from_csv(csv).unwrap();
+        let summary = sites
+            .iter()
+            .map(|s| {
+                (
+                    s.site_name.as_str(),
+                    s.login_name.as_str(),
+                    s.notes.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("bank.co.uk", "jane", "main account"),
+                ("Router", "admin", "")
+            ]
+        );
+        assert!(sites.iter().all(|s| s.counter == 1
+            && s.password_type == "Maximum"
+            && s.migration == Some(MigrationStatus::Pending)));
+    }
+
+    #[test]
+    fn from_csv_requires_name_or_url_column() {
+        assert!(from_csv("").is_err());
+        assert!(from_csv("username,password\njane,secret\n").is_err());
+    }
+
+    #[test]
+    fn from_bitwarden_imports_login_items_only() {
+        let json = r#"{"items": [
+            {"name": "Example", "notes": null,
+             "login": {"username": "jane", "uris": [{"uri": null}, {"uri": "https://www.example.com/login"}]}},
+            {"name": "Secure note", "notes": "not an account"}
+        ]}"#;
+        let sites = from_bitwarden(json).unwrap();
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].site_name, "example.com");
+        assert_eq!(sites[0].login_name, "jane");
+        assert_eq!(sites[0].url, "https://www.example.com/login");
+    }
+}
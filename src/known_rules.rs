@@ -0,0 +1,135 @@
+//! Known password requirements of popular sites
+//!
+//! Curated after Apple's password rules quirks
+//! (https://github.com/apple/password-manager-resources/blob/main/quirks/password-rules.json).
+
+use crate::{
+    domain::domain_from_url,
+    related_domains::{is_same_or_subdomain, related_domains},
+};
+
+/// Password requirements of a domain (and its subdomains) in the passwordrules syntax
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KnownRules {
+    pub domain: &'static str,
+    pub rules: &'static str,
+}
+
+const fn known(domain: &'static str, rules: &'static str) -> KnownRules {
+    KnownRules { domain, rules }
+}
+
+const KNOWN_RULES: &[KnownRules] = &[
+    known(
+        "americanexpress.com",
+        "minlength: 8; maxlength: 20; max-consecutive: 4; required: lower, upper; required: digit; allowed: [%&_?#=];",
+    ),
+    known(
+        "apple.com",
+        "minlength: 8; maxlength: 63; required: lower; required: upper; required: digit; allowed: ascii-printable;",
+    ),
+    known(
+        "bankofamerica.com",
+        "minlength: 8; maxlength: 20; max-consecutive: 3; required: lower; required: upper; required: digit; allowed: [-@#*()+={}/?~;,._];",
+    ),
+    known(
+        "battle.net",
+        "minlength: 8; maxlength: 16; required: lower, upper; allowed: digit, special;",
+    ),
+    known(
+        "chase.com",
+        "minlength: 8; maxlength: 32; max-consecutive: 2; required: lower, upper; required: digit; required: [!#$%+/=@~];",
+    ),
+    known(
+        "citi.com",
+        "minlength: 8; maxlength: 64; max-consecutive: 2; required: digit; required: upper; required: lower; required: [-~`!@#$%^&*()_\\/|];",
+    ),
+    known(
+        "comcast.net",
+        "minlength: 8; maxlength: 32; required: lower; required: upper; required: digit; allowed: [!#$%&*+=?@^_|~];",
+    ),
+    known(
+        "dell.com",
+        "minlength: 8; maxlength: 20; required: lower; required: upper; required: digit; required: [!#$%&*+=?@^_|~-];",
+    ),
+    known(
+        "delta.com",
+        "minlength: 8; maxlength: 20; required: lower; required: upper; required: digit;",
+    ),
+    known(
+        "discover.com",
+        "minlength: 8; maxlength: 32; max-consecutive: 2; required: lower, upper; required: digit; allowed: [@$.-_];",
+    ),
+    known(
+        "ebay.com",
+        "minlength: 6; maxlength: 64; required: lower, upper; required: digit; allowed: [!@#$%^&*];",
+    ),
+    known(
+        "hsbc.com",
+        "minlength: 8; maxlength: 30; required: lower; required: upper; required: digit; allowed: [-!$*.=?@_'];",
+    ),
+    known(
+        "paypal.com",
+        "minlength: 8; maxlength: 20; max-consecutive: 3; required: lower, upper; required: digit, [!@#$%^&*()];",
+    ),
+    known(
+        "southwest.com",
+        "minlength: 8; maxlength: 16; required: upper; required: digit; allowed: lower, [!@#$%^*(),.;:/\\];",
+    ),
+    known(
+        "target.com",
+        "minlength: 8; maxlength: 20; required: lower, upper; required: digit, [-!\"#$%&'()*+,./:;=?@[\\^_`{|}~];",
+    ),
+    known(
+        "united.com",
+        "minlength: 8; maxlength: 32; required: lower; required: upper; required: digit; required: [-!#$%&*?@^_~];",
+    ),
+    known(
+        "vanguard.com",
+        "minlength: 6; maxlength: 20; required: lower; required: upper; required: digit; allowed: [-!\"#$%&'()*+,./:;<=>?@^_`{|}~];",
+    ),
+    known(
+        "wellsfargo.com",
+        "minlength: 8; maxlength: 32; required: lower; required: upper; required: digit;",
+    ),
+];
+
+/// Known password requirements of a site name (or URL), also via domains sharing one account
+pub fn known_rules(site_name: &str) -> Option<KnownRules> {
+    let domain = domain_from_url(site_name)?;
+    let related = related_domains(&domain);
+    KNOWN_RULES.iter().copied().find(|known| {
+        is_same_or_subdomain(&domain, known.domain)
+            || related
+                .iter()
+                .any(|d| is_same_or_subdomain(d, known.domain))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::password_rules::PasswordRules;
+
+    #[test]
+    fn all_known_rules_parse() {
+        for known in KNOWN_RULES {
+            PasswordRules::parse(known.rules)
+                .unwrap_or_else(|e| panic!("rules of {}: {}", known.domain, e));
+        }
+    }
+
+    #[test]
+    fn known_rules_of_subdomains_and_related_domains() {
+        assert_eq!(
+            known_rules("https://secure.chase.com/login").map(|k| k.domain),
+            Some("chase.com")
+        );
+        assert_eq!(
+            known_rules("icloud.com").map(|k| k.domain),
+            Some("apple.com")
+        );
+        assert_eq!(known_rules("example.com"), None);
+        assert_eq!(known_rules("chase"), None);
+    }
+}
@@ -3,7 +3,11 @@ use std::error::Error;
 use leptos::*;
 use mpw::masterkey::MasterKey;
 
-use crate::{storage::EncryptedStorage, LoginData, RwLoginData, RwStorage};
+use crate::{
+    storage::EncryptedStorage,
+    strength::{estimate, score_label, StrengthMeter},
+    LoginData, RwLoginData, RwStorage,
+};
 
 const STORAGE_PASSWORD_SITE: &str = "__storage__";
 const STORAGE_PASSWORD_TYPE: &str = "Maximum";
@@ -14,6 +18,18 @@ fn try_login(
     password: &str,
     storage: &EncryptedStorage,
 ) -> Result<LoginData, Box<dyn Error>> {
+    // new identities need a sufficiently strong master password (existing identities with a weaker
+    // one can only be used after lowering the minimum in the options)
+    if !storage.is_known_user(name)
+        && estimate(password, &[name]).score < storage.min_password_score
+    {
+        return Err(format!(
+            "The master password of a new identity needs to be at least {}",
+            score_label(storage.min_password_score).0
+        )
+        .into());
+    }
+
     let masterkey = MasterKey::new_auth(&name, &password);
 
     let storage_password = masterkey.generate_password(
@@ -39,9 +55,13 @@ pub fn Login(cx: Scope) -> impl IntoView {
 
     let name = create_rw_signal(cx, store().last_user);
     let password = create_rw_signal(cx, "".to_string());
-    let pw_invalid = create_rw_signal(cx, false);
+    let error = create_rw_signal(cx, None::<String>);
     let hide_pw = create_rw_signal(cx, true);
 
+    // derived signals
+    let is_new_user = move || !name().is_empty() && !store().is_known_user(&name());
+    let min_score = Signal::derive(cx, move || store().min_password_score);
+
     // null password upon login
     create_effect(cx, move |_| {
         if login_data().is_some() {
@@ -66,6 +86,7 @@ pub fn Login(cx: Scope) -> impl IntoView {
                             </span>
                             <input type="text" class="form-control" placeholder="Full Name"
                                 on:input=move |ev| {
+                                    error.set(None);
                                     name.set(event_target_value(&ev));
                                 }
                             prop:value=name
@@ -82,9 +103,9 @@ pub fn Login(cx: Scope) -> impl IntoView {
                             <input
                                 type=move || if hide_pw() { "password" } else { "text" }
                                 placeholder="Password"
-                                class=move || {if pw_invalid() {"form-control is-invalid"} else {"form-control"}}
+                                class=move || {if error().is_some() {"form-control is-invalid"} else {"form-control"}}
                                 on:input=move |ev| {
-                                    pw_invalid.set(false);
+                                    error.set(None);
                                     password.set(event_target_value(&ev));
                                 }
                             prop:value=password
@@ -98,8 +119,16 @@ pub fn Login(cx: Scope) -> impl IntoView {
                                 <i class=move || if hide_pw() {"fa-solid fa-eye"} else {"fa-solid fa-eye-slash"} />
                             </button>
                         </div>
+                        {move || error().map(|e| view! { cx, <div class="invalid-feedback d-block text-start">{e}</div> })}
                     </div>
 
+                    // Strength of the master password of a new identity
+                    <Show when=is_new_user fallback=|_| ()>
+                        <div class="row mb-3 px-3 text-start">
+                            <small class="mb-1">"New identity: choose a strong master password, it cannot be changed later."</small>
+                            <StrengthMeter password=password name=name min_score=min_score />
+                        </div>
+                    </Show>
 
                     // Submit button
                     <div class="row mb-3 px-3">
@@ -111,13 +140,36 @@ pub fn Login(cx: Scope) -> impl IntoView {
                                 if name().len() > 0 {
                                     try_login(&name(), &password(), &store())
                                         .map(|data| login_data.set(Some(data)))
-                                        .unwrap_or_else(|_| {
-                                            pw_invalid.set(true);
-                                    });
+                                        .unwrap_or_else(|e| error.set(Some(e.to_string())));
                                 }
                             }
                         >"Submit"</button>
                     </div>
+
+                    // Options
+                    <details class="text-start">
+                        <summary><small class="text-muted">"Options"</small></summary>
+                        <div class="input-group input-group-sm mt-2">
+                            <span class="input-group-text">"New identities need a password that is at least"</span>
+                            <select class="form-select"
+                                on:change=move |ev| {
+                                    let score = event_target_value(&ev).parse().unwrap_or(0);
+                                    store.update(|s| s.min_password_score = score);
+                                }
+                                prop:value=move || min_score().to_string()
+                            >
+                                {(0..=4u8).map(|score| view! { cx,
+                                    <option value=score.to_string() selected=move || min_score() == score>
+                                        {score_label(score).0}
+                                    </option>
+                                })
+                                .collect::<Vec<_>>()}
+                            </select>
+                        </div>
+                        <small class="text-muted">
+                            "Only lower the minimum to use an existing identity with a weaker master password on this device."
+                        </small>
+                    </details>
                 </form>
             </div>
         </div>
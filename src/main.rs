@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use leptos::*;
 use mpw::masterkey::MasterKey;
 
@@ -10,6 +12,44 @@ use storage::EncryptedStorage;
 mod sites;
 use sites::*;
 
+mod backup;
+mod breach;
+use breach::BreachFilter;
+mod bulk;
+mod domain;
+mod entropy;
+mod groups;
+mod history;
+mod import;
+mod known_rules;
+mod merge;
+
+mod migration;
+use migration::*;
+
+mod password_rules;
+
+mod navigation;
+use navigation::*;
+
+mod query;
+mod related_domains;
+mod report;
+use report::*;
+mod rotation;
+mod search;
+
+mod settings;
+mod strength;
+
+mod trash;
+use trash::*;
+
+mod sync;
+use sync::*;
+
+mod webdav;
+
 fn main() {
     leptos::mount_to_body(|cx| view! { cx, <App/> })
 }
@@ -20,6 +60,18 @@ struct RwStorage(RwSignal<EncryptedStorage>);
 #[derive(Copy, Clone)]
 struct RwLoginData(RwSignal<Option<LoginData>>);
 
+#[derive(Copy, Clone)]
+struct RwPage(RwSignal<Page>);
+
+#[derive(Copy, Clone)]
+struct RwSiteFilter(RwSignal<String>);
+
+#[derive(Copy, Clone)]
+struct RwUndo(RwSignal<Option<Undo>>);
+
+#[derive(Copy, Clone)]
+struct RwBreachFilter(RwSignal<Option<Rc<BreachFilter>>>);
+
 #[derive(Clone, Debug)]
 pub struct LoginData {
     name: String,
@@ -33,10 +85,42 @@ fn App(cx: Scope) -> impl IntoView {
     let login_data = create_rw_signal::<Option<LoginData>>(cx, None);
     provide_context(cx, RwLoginData(login_data));
 
+    // prepare global state for the currently shown page
+    let page = create_rw_signal(cx, Page::Sites);
+    provide_context(cx, RwPage(page));
+
     // prepare global state for browser-local storage
     let store = create_rw_signal(cx, EncryptedStorage::from_local_storage());
     provide_context(cx, RwStorage(store));
 
+    // prepare global state for the filter of the site list (set e.g. from the report page)
+    let site_filter = create_rw_signal(cx, "".to_string());
+    provide_context(cx, RwSiteFilter(site_filter));
+
+    // prepare global state for undoing deletions
+    let undo = create_rw_signal::<Option<Undo>>(cx, None);
+    provide_context(cx, RwUndo(undo));
+
+    // prepare global state for the breached password filter (kept in IndexedDB)
+    let breach_filter = create_rw_signal::<Option<Rc<BreachFilter>>>(cx, None);
+    provide_context(cx, RwBreachFilter(breach_filter));
+    spawn_local(async move {
+        match breach::load_filter().await {
+            Ok(filter) => breach_filter.set(filter.map(Rc::new)),
+            Err(e) => log!("Error loading the breached password filter: {}", e),
+        }
+    });
+
+    // purge expired sites from the trash upon login
+    create_effect(cx, move |_| {
+        if let Some(login) = login_data() {
+            store.update(|data| {
+                data.purge_expired_trash(&login.name, &login.storage_password)
+                    .unwrap_or_else(|e| log!("Error purging the trash: {}", e))
+            });
+        }
+    });
+
     // write database to storage whenever it changes
     create_effect(cx, move |_| {
         store().to_local_storage();
@@ -49,7 +133,15 @@ fn App(cx: Scope) -> impl IntoView {
                 // if no masterpassword is set, yet, show login component
                 fallback=move |cx| view! { cx, <Login />}
             >
-                <Sites />
+                <Navigation />
+                {move || match page() {
+                    Page::Sites => view! { cx, <Sites /> }.into_view(cx),
+                    Page::Migration => view! { cx, <Migration /> }.into_view(cx),
+                    Page::Sync => view! { cx, <Synchronization /> }.into_view(cx),
+                    Page::Report => view! { cx, <Report /> }.into_view(cx),
+                    Page::Trash => view! { cx, <Trash /> }.into_view(cx),
+                }}
+                <UndoToast />
             </Show>
         </div>
     }
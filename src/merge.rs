@@ -0,0 +1,596 @@
+use std::collections::{BTreeMap, HashMap};
+
+use leptos::*;
+
+use crate::{sites::Site, storage::format_timestamp, RwLoginData, RwStorage};
+
+/// Classification of an incoming site with respect to the sites already stored
+#[derive(Clone, Debug, PartialEq)]
+pub enum MergeStatus {
+    /// no site with the same name is stored (or imported before), yet
+    New,
+    /// a site with the same name and settings is stored (or imported before)
+    Identical,
+    /// a site with the same name but different settings is stored (or imported before, e.g. a
+    /// second account on the same host); the differing fields are listed
+    Conflict(Vec<&'static str>),
+}
+
+/// How to resolve a conflict between a stored site ("mine") and an incoming site ("theirs")
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Resolution {
+    KeepMine,
+    TakeTheirs,
+    KeepNewest,
+}
+
+impl Resolution {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "theirs" => Resolution::TakeTheirs,
+            "newest" => Resolution::KeepNewest,
+            _ => Resolution::KeepMine,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::KeepMine => "mine",
+            Resolution::TakeTheirs => "theirs",
+            Resolution::KeepNewest => "newest",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MergeItem {
+    pub mine: Option<Site>,
+    pub theirs: Site,
+    pub status: MergeStatus,
+    pub resolution: Resolution,
+}
+
+impl MergeItem {
+    /// The site that ends up in the store after the merge
+    pub fn resolved(&self) -> Site {
+        match (&self.mine, self.resolution) {
+            (None, _) | (Some(_), Resolution::TakeTheirs) => self.theirs.clone(),
+            (Some(mine), Resolution::KeepMine) => mine.clone(),
+            (Some(mine), Resolution::KeepNewest) => {
+                if self.theirs.modified > mine.modified {
+                    self.theirs.clone()
+                } else {
+                    mine.clone()
+                }
+            }
+        }
+    }
+}
+
+/// Names of the fields (apart from the site name) in which two sites differ
+pub fn differing_fields(mine: &Site, theirs: &Site) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if mine.counter != theirs.counter {
+        fields.push("counter");
+    }
+    if mine.password_type != theirs.password_type {
+        fields.push("password type");
+    }
+    if mine.label != theirs.label {
+        fields.push("label");
+    }
+    if mine.aliases != theirs.aliases {
+        fields.push("aliases");
+    }
+    if mine.trashed.is_some() != theirs.trashed.is_some() {
+        fields.push("trash");
+    }
+    if mine.rules != theirs.rules {
+        fields.push("password rules");
+    }
+    if mine.rotation_days != theirs.rotation_days {
+        fields.push("rotation policy");
+    }
+    if mine.group != theirs.group {
+        fields.push("group");
+    }
+    if mine.favorite != theirs.favorite {
+        fields.push("favorite");
+    }
+    if mine.tags != theirs.tags {
+        fields.push("tags");
+    }
+    if mine.login_name != theirs.login_name {
+        fields.push("login");
+    }
+    if mine.url != theirs.url {
+        fields.push("URL");
+    }
+    if mine.notes != theirs.notes {
+        fields.push("notes");
+    }
+    if mine.migration != theirs.migration {
+        fields.push("migration status");
+    }
+    fields
+}
+
+/// Compare each incoming site against the stored site of the same name
+///
+/// Site names are unique, so an incoming site whose name is not stored but was already imported
+/// by an earlier item is compared against that one instead.
+pub fn classify(mine: &[Site], theirs: &[Site]) -> Vec<MergeItem> {
+    let mut imported: Vec<Site> = Vec::new();
+    theirs
+        .iter()
+        // sites in the trash of the backup are not imported
+        .filter(|their_site| their_site.is_live())
+        .map(|their_site| {
+            let my_site = mine
+                .iter()
+                .chain(imported.iter())
+                .find(|s| s.is_live() && s.site_name == their_site.site_name)
+                .cloned();
+            if my_site.is_none() {
+                imported.push(their_site.clone());
+            }
+            let status = match &my_site {
+                None => MergeStatus::New,
+                Some(my_site) => {
+                    let fields = differing_fields(my_site, their_site);
+                    if fields.is_empty() {
+                        MergeStatus::Identical
+                    } else {
+                        MergeStatus::Conflict(fields)
+                    }
+                }
+            };
+            MergeItem {
+                mine: my_site,
+                theirs: their_site.clone(),
+                status,
+                resolution: Resolution::KeepMine,
+            }
+        })
+        .collect()
+}
+
+/// Apply the (resolved) merge items to the stored sites
+///
+/// Imported sites are changes to this copy of the vault: new sites get a fresh ID and changed
+/// sites keep their ID but advance their clock.
+pub fn merge(mine: &[Site], items: &[MergeItem]) -> Vec<Site> {
+    let mut sites = mine.to_vec();
+    for item in items {
+        match item.status {
+            MergeStatus::Identical => {}
+            MergeStatus::New => {
+                let resolved = item.resolved();
+                // never store two sites of the same name
+                if !sites
+                    .iter()
+                    .any(|s| s.is_live() && s.site_name == resolved.site_name)
+                {
+                    sites.push(Site {
+                        id: Site::new_id(),
+                        clock: 1,
+                        ..resolved
+                    });
+                }
+            }
+            MergeStatus::Conflict(_) => {
+                let resolved = item.resolved();
+                if let Some(site) = sites
+                    .iter_mut()
+                    .find(|s| s.is_live() && s.site_name == resolved.site_name)
+                {
+                    if *site != resolved {
+                        *site = Site {
+                            id: site.id.clone(),
+                            clock: site.clock.max(resolved.clock) + 1,
+                            ..resolved
+                        };
+                    }
+                }
+            }
+        }
+    }
+    sites
+}
+
+/// Ordering of two versions of the same site: the one with more changes wins
+///
+/// Ties are broken by modification time and finally by content, so that the result does not
+/// depend on which copy is "mine" and which is "theirs".
+fn version(site: &Site) -> (u64, u64, bool, String) {
+    (
+        site.clock,
+        site.modified,
+        site.deleted,
+        serde_json::to_string(site).unwrap(),
+    )
+}
+
+/// The newer version of a site with the usage of both versions
+///
+/// Using a password does not advance the site's clock, so usage is merged separately.
+fn with_usage(newer: &Site, older: &Site) -> Site {
+    if newer.deleted {
+        return newer.clone();
+    }
+    Site {
+        last_used: newer.last_used.max(older.last_used),
+        use_count: newer.use_count.max(older.use_count),
+        ..newer.clone()
+    }
+}
+
+/// Deterministically merge two copies of a vault (including the tombstones of deleted sites)
+///
+/// Sites are matched by their ID and the newer version of each site is kept. Merging is
+/// commutative and idempotent, so all devices arrive at the same result.
+pub fn merge_vaults(mine: &[Site], theirs: &[Site]) -> Vec<Site> {
+    let mut merged: BTreeMap<String, Site> = BTreeMap::new();
+    for site in mine.iter().chain(theirs) {
+        let merged_site = match merged.get(&site.id) {
+            Some(existing) if version(existing) >= version(site) => with_usage(existing, site),
+            Some(existing) => with_usage(site, existing),
+            None => site.clone(),
+        };
+        merged.insert(site.id.clone(), merged_site);
+    }
+
+    // sites created independently on two devices may share a name: keep the newer one only
+    // (trashed sites may share the name of a live site, see `restore_sites`)
+    let mut sites = merged.into_values().collect::<Vec<_>>();
+    let mut by_name: HashMap<String, usize> = HashMap::new();
+    for idx in 0..sites.len() {
+        if !sites[idx].is_live() {
+            continue;
+        }
+        let name = sites[idx].site_name.clone();
+        let Some(&other) = by_name.get(&name) else {
+            by_name.insert(name, idx);
+            continue;
+        };
+        let loser = if version(&sites[idx]) > version(&sites[other]) {
+            by_name.insert(name, idx);
+            other
+        } else {
+            idx
+        };
+        sites[loser] = sites[loser].tombstone(sites[loser].modified);
+    }
+
+    sites
+}
+
+#[component]
+pub fn MergeReview(cx: Scope, items: RwSignal<Option<Vec<MergeItem>>>) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // derived signals
+    let count = move |matches: fn(&MergeStatus) -> bool| {
+        items.with(|items| {
+            items
+                .iter()
+                .flatten()
+                .filter(|item| matches(&item.status))
+                .count()
+        })
+    };
+    let conflicts = move || {
+        items()
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, item)| match item.status.clone() {
+                MergeStatus::Conflict(fields) => Some((idx, item, fields)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let set_resolution = move |idx: usize, resolution: Resolution| {
+        items.update(|items| {
+            if let Some(item) = items.as_mut().and_then(|items| items.get_mut(idx)) {
+                item.resolution = resolution;
+            }
+        });
+    };
+
+    let apply = move |_| {
+        if let Some(merge_items) = items() {
+            store.update(|data| {
+                data.decrypt_all_sites(&login_name(), &storage_password())
+                    .map(|sites| {
+                        data.store_sites(
+                            &login_name(),
+                            &storage_password(),
+                            &merge(&sites, &merge_items),
+                        )
+                    })
+                    .unwrap_or_else(|e| log!("Error merging sites: {}", e));
+            });
+        }
+        items.set(None);
+    };
+
+    view! { cx,
+        <Show when=move || items.with(|items| items.is_some()) fallback=|_| ()>
+            <div class="card mb-3 border-dark">
+                <div class="card-header text-bg-secondary text-bg-override">
+                    <span class="fs-4">"Review Import"</span>
+                </div>
+                <div class="card-body text-bg-light">
+                    <p>
+                        {move || count(|s| *s == MergeStatus::New)}" new, "
+                        {move || count(|s| *s == MergeStatus::Identical)}" identical, "
+                        {move || count(|s| matches!(s, MergeStatus::Conflict(_)))}" conflicting"
+                    </p>
+
+                    <table class="table table-sm">
+                        <thead>
+                            <tr>
+                                <th>"Site"</th>
+                                <th>"Differs in"</th>
+                                <th>"Mine"</th>
+                                <th>"Theirs"</th>
+                                <th>"Keep"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {move || conflicts().into_iter().map(|(idx, item, fields)| {
+                                let mine = item.mine.clone().unwrap_or_default();
+                                view! { cx,
+                                    <tr>
+                                        <td>{item.theirs.site_name.clone()}</td>
+                                        <td>{fields.join(", ")}</td>
+                                        <td>
+                                            {format!("{} / {}", mine.password_type, mine.counter)}
+                                            <br /><small class="text-muted">{format_timestamp(mine.modified)}</small>
+                                        </td>
+                                        <td>
+                                            {format!("{} / {}", item.theirs.password_type, item.theirs.counter)}
+                                            <br /><small class="text-muted">{format_timestamp(item.theirs.modified)}</small>
+                                        </td>
+                                        <td>
+                                            <select
+                                                class="form-select form-select-sm"
+                                                on:change=move |ev| set_resolution(idx, Resolution::parse(&event_target_value(&ev)))
+                                                prop:value=item.resolution.as_str()
+                                            >
+                                                <option value="mine">"Keep mine"</option>
+                                                <option value="theirs">"Take theirs"</option>
+                                                <option value="newest">"Keep newest"</option>
+                                            </select>
+                                        </td>
+                                    </tr>
+                                }
+                            })
+                            .collect::<Vec<_>>()}
+                        </tbody>
+                    </table>
+
+                    <div class="text-end">
+                        <button class="btn btn-light btn-outline-secondary me-2" type="button" on:click=move |_| items.set(None)>
+                            "Cancel"
+                        </button>
+                        <button class="btn btn-secondary text-bg-override" type="button" on:click=apply>
+                            "Apply"
+                        </button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(id: &str, site_name: &str, counter: i32) -> Site {
+        Site {
+            id: id.to_string(),
+            clock: 1,
+            site_name: site_name.to_string(),
+            counter,
+            password_type: "Maximum".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn classify_compares_against_stored_and_earlier_imported_sites() {
+        let mine = vec![stored("a", "example.com", 1), stored("b", "other.org", 1)];
+        let theirs = vec![
+            stored("", "example.com", 1),
+            stored("", "other.org", 2),
+            stored("", "new.net", 1),
+            // a second account on the same host within the same import
+            stored("", "new.net", 3),
+            stored("", "new.net", 1),
+        ];
+
+        let statuses = classify(&mine, &theirs)
+            .into_iter()
+            .map(|item| item.status)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                MergeStatus::Identical,
+                MergeStatus::Conflict(vec!["counter"]),
+                MergeStatus::New,
+                MergeStatus::Conflict(vec!["counter"]),
+                MergeStatus::Identical,
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_applies_resolutions_without_duplicating_names() {
+        let mine = vec![stored("a", "example.com", 1), stored("b", "other.org", 1)];
+        let theirs = vec![
+            stored("", "example.com", 2),
+            stored("", "other.org", 2),
+            stored("", "new.net", 1),
+            stored("", "new.net", 3),
+        ];
+        let mut items = classify(&mine, &theirs);
+        items[0].resolution = Resolution::TakeTheirs;
+        items[3].resolution = Resolution::TakeTheirs;
+
+        let sites = merge(&mine, &items);
+        let summary = sites
+            .iter()
+            .map(|s| (s.site_name.as_str(), s.counter, s.clock))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("example.com", 2, 2),
+                ("other.org", 1, 1),
+                ("new.net", 3, 2)
+            ]
+        );
+        // changed sites keep their ID, the new one gets a fresh one
+        assert_eq!(sites[0].id, "a");
+        assert!(!sites[2].id.is_empty());
+    }
+
+    #[test]
+    fn merge_vaults_keeps_the_newer_version_of_each_site() {
+        let base = stored("a", "example.com", 1);
+        let mine = vec![
+            Site {
+                clock: 2,
+                counter: 2,
+                ..base.clone()
+            },
+            stored("b", "mine.org", 1),
+        ];
+        let theirs = vec![
+            base.clone(),
+            stored("b", "mine.org", 1).tombstone(5),
+            stored("c", "theirs.net", 1),
+        ];
+
+        let merged = merge_vaults(&mine, &theirs);
+        assert_eq!(merged, merge_vaults(&theirs, &mine));
+        assert_eq!(merged, merge_vaults(&merged, &theirs));
+
+        let summary = merged
+            .iter()
+            .map(|s| (s.id.as_str(), s.counter, s.deleted))
+            .collect::<Vec<_>>();
+        // the deletion is not undone by the older live copy
+        assert_eq!(
+            summary,
+            vec![("a", 2, false), ("b", 0, true), ("c", 1, false)]
+        );
+    }
+
+    #[test]
+    fn merge_vaults_keeps_one_site_per_name() {
+        let mine = vec![Site {
+            modified: 10,
+            ..stored("a", "example.com", 1)
+        }];
+        let theirs = vec![Site {
+            modified: 20,
+            ..stored("b", "example.com", 2)
+        }];
+
+        let merged = merge_vaults(&mine, &theirs);
+        assert_eq!(merged, merge_vaults(&theirs, &mine));
+        let live = merged.iter().filter(|s| !s.deleted).collect::<Vec<_>>();
+        assert_eq!(live.len(), 1);
+        assert_eq!((live[0].id.as_str(), live[0].counter), ("b", 2));
+        // the other copy becomes a tombstone so that it is not resurrected by later merges
+        assert!(merged.iter().any(|s| s.id == "a" && s.deleted));
+    }
+
+    #[test]
+    fn merge_vaults_keeps_live_sites_sharing_the_name_of_a_trashed_one() {
+        let trashed = Site {
+            clock: 5,
+            modified: 20,
+            trashed: Some(20),
+            ..stored("a", "example.com", 1)
+        };
+        let live = Site {
+            modified: 10,
+            ..stored("b", "example.com", 2)
+        };
+
+        let merged = merge_vaults(&[trashed.clone()], &[live.clone()]);
+        assert_eq!(merged, merge_vaults(&[live.clone()], &[trashed.clone()]));
+        assert_eq!(merged, vec![trashed, live]);
+    }
+
+    #[test]
+    fn merge_vaults_keeps_usage_of_both_copies() {
+        let site = stored("a", "example.com", 1);
+        let used_here = Site {
+            last_used: 100,
+            use_count: 3,
+            ..site.clone()
+        };
+        let changed_there = Site {
+            clock: 2,
+            counter: 2,
+            last_used: 50,
+            use_count: 1,
+            ..site.clone()
+        };
+
+        let merged = merge_vaults(&[used_here.clone()], &[changed_there.clone()]);
+        assert_eq!(merged, merge_vaults(&[changed_there], &[used_here]));
+        assert_eq!(
+            (merged[0].counter, merged[0].last_used, merged[0].use_count),
+            (2, 100, 3)
+        );
+    }
+
+    #[test]
+    fn trashed_sites_do_not_take_part_in_imports() {
+        let mine = vec![Site {
+            trashed: Some(1),
+            ..stored("a", "example.com", 1)
+        }];
+        let theirs = vec![stored("", "example.com", 2)];
+
+        let items = classify(&mine, &theirs);
+        assert_eq!(items[0].status, MergeStatus::New);
+        let sites = merge(&mine, &items);
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0], mine[0]);
+        assert_eq!((sites[1].counter, sites[1].trashed), (2, None));
+    }
+
+    #[test]
+    fn sites_trashed_in_the_backup_are_not_imported() {
+        let theirs = vec![
+            Site {
+                trashed: Some(1),
+                ..stored("", "trashed.com", 1)
+            },
+            stored("", "deleted.com", 1).tombstone(1),
+            stored("", "live.com", 1),
+        ];
+
+        let items = classify(&[], &theirs);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].theirs.site_name, "live.com");
+        let sites = merge(&[], &items);
+        assert_eq!(sites.len(), 1);
+        assert!(sites[0].is_live());
+    }
+}
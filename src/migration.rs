@@ -0,0 +1,231 @@
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{sites::Site, RwLoginData, RwStorage};
+
+/// Progress of moving an account onto its generated password
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+pub enum MigrationStatus {
+    /// the account still uses the password from the previous password manager
+    Pending,
+    /// the password has been changed on the site but logging in with it was not confirmed, yet
+    Changed,
+    /// logging in with the generated password has been confirmed
+    Verified,
+}
+
+impl MigrationStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MigrationStatus::Pending => "pending",
+            MigrationStatus::Changed => "changed",
+            MigrationStatus::Verified => "verified",
+        }
+    }
+}
+
+#[component]
+pub fn Migration(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // signals
+    let selected = create_rw_signal::<Option<String>>(cx, None);
+
+    // derived signals
+    let migrated_sites = move || {
+        store()
+            .decrypt_sites(&login_name(), &storage_password())
+            .unwrap_or(Vec::new())
+            .into_iter()
+            .filter(|s| s.migration.is_some())
+            .collect::<Vec<_>>()
+    };
+    let count = move |status: MigrationStatus| {
+        migrated_sites()
+            .iter()
+            .filter(|s| s.migration == Some(status))
+            .count()
+    };
+    let progress = move || {
+        let total = migrated_sites().len();
+        if total == 0 {
+            100
+        } else {
+            100 * count(MigrationStatus::Verified) / total
+        }
+    };
+    let open_sites = move || {
+        migrated_sites()
+            .into_iter()
+            .filter(|s| s.migration != Some(MigrationStatus::Verified))
+            .collect::<Vec<_>>()
+    };
+    let selected_site =
+        move || selected().and_then(|name| open_sites().into_iter().find(|s| s.site_name == name));
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-header text-bg-secondary text-bg-override">
+                <span class="fs-4">"Migration Progress"</span>
+            </div>
+            <div class="card-body text-bg-light">
+                <div class="progress mb-2">
+                    <div class="progress-bar bg-success" style=move || format!("width: {}%", progress())>
+                        {move || format!("{}%", progress())}
+                    </div>
+                </div>
+                <span class="badge text-bg-warning me-2">{move || count(MigrationStatus::Pending)}" pending"</span>
+                <span class="badge text-bg-info me-2">{move || count(MigrationStatus::Changed)}" changed"</span>
+                <span class="badge text-bg-success">{move || count(MigrationStatus::Verified)}" verified"</span>
+            </div>
+        </div>
+
+        {move || selected_site().map(|site| view! { cx, <MigrationGuide site=site selected=selected /> })}
+
+        <ul class="list-group mb-3">
+            {move || open_sites().into_iter().map(|site| {
+                let site_name = site.site_name.clone();
+                let status = site.migration.map(|m| m.label()).unwrap_or_default();
+                view! { cx,
+                    <li class="list-group-item d-flex justify-content-between align-items-center">
+                        <span>
+                            {site.display_name().to_string()}
+                            <small class="text-muted ms-2">{site.login_name.clone()}</small>
+                        </span>
+                        <span>
+                            <span class="badge text-bg-secondary me-2">{status}</span>
+                            <button class="btn btn-sm btn-light btn-outline-secondary" type="button"
+                                on:click=move |_| selected.set(Some(site_name.clone()))
+                            >
+                                "Migrate"
+                            </button>
+                        </span>
+                    </li>
+                }
+            })
+            .collect::<Vec<_>>()}
+        </ul>
+    }
+}
+
+/// Step-by-step guide for switching one account to its generated password
+#[component]
+fn MigrationGuide(cx: Scope, site: Site, selected: RwSignal<Option<String>>) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let masterkey = move || login_data().unwrap().masterkey;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // signals
+    let hide_pw = create_rw_signal(cx, true);
+
+    let password = masterkey().generate_password(
+        &site.site_name,
+        &site.password_type.as_str().into(),
+        site.counter,
+    );
+    let changed = site.migration == Some(MigrationStatus::Changed);
+    let first_step_class = if changed { "text-muted" } else { "" };
+    let login_url = if site.url.is_empty() {
+        site.site_name.clone()
+    } else {
+        site.url.clone()
+    };
+
+    let set_status = {
+        let site = site.clone();
+        move |status: MigrationStatus| {
+            let site = Site {
+                migration: Some(status),
+                ..site.clone()
+            };
+            store.update(|data| {
+                data.update_site(&login_name(), &storage_password(), site)
+                    .unwrap_or_else(|e| log!("Error updating site: {}", e));
+            });
+            if status == MigrationStatus::Verified {
+                selected.set(None);
+            }
+        }
+    };
+    let set_status_back = set_status.clone();
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-header">
+                <div class="row">
+                    <span class="col-8 fs-4">{site.display_name().to_string()}</span>
+                    <div class="col-4">
+                        <button class="btn btn-light btn-outline-secondary float-end" type="button"
+                            on:click=move |_| selected.set(None)
+                        >
+                            <i class="fa-solid fa-xmark" />
+                        </button>
+                    </div>
+                </div>
+            </div>
+            <div class="card-body text-bg-light">
+                <ol>
+                    <li class=first_step_class>
+                        "Log in to "{login_url}" as "<b>{site.login_name.clone()}</b>
+                        " and change the password to:"
+                    </li>
+                </ol>
+
+                // Password field
+                <div class="input-group mb-3">
+                    <input
+                        class="form-control text-bg-secondary text-bg-override text-center"
+                        type=move || if hide_pw() { "password" } else { "text" }
+                        prop:value=password
+                        readonly
+                    />
+                    <button
+                        // toggle password hiding
+                        class="btn btn-light btn-outline-secondary"
+                        type="button"
+                        on:click=move |_| hide_pw.set(!hide_pw())
+                    >
+                        <i class=move || if hide_pw() {"fa-solid fa-eye"} else {"fa-solid fa-eye-slash"} />
+                    </button>
+                </div>
+
+                {if changed {
+                    view! { cx,
+                        <div>
+                            <p>"Log out and log in again with the new password to confirm that it works."</p>
+                            <button class="btn btn-light btn-outline-secondary me-2" type="button"
+                                on:click=move |_| set_status_back(MigrationStatus::Pending)
+                            >
+                                "Not changed, yet"
+                            </button>
+                            <button class="btn btn-secondary text-bg-override" type="button"
+                                on:click=move |_| set_status(MigrationStatus::Verified)
+                            >
+                                <i class="fa-solid fa-check" />" Login works"
+                            </button>
+                        </div>
+                    }
+                } else {
+                    view! { cx,
+                        <div>
+                            <button class="btn btn-secondary text-bg-override" type="button"
+                                on:click=move |_| set_status(MigrationStatus::Changed)
+                            >
+                                <i class="fa-solid fa-check" />" Password changed"
+                            </button>
+                        </div>
+                    }
+                }}
+            </div>
+        </div>
+    }
+}
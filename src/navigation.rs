@@ -0,0 +1,74 @@
+use leptos::*;
+
+use crate::{RwLoginData, RwPage};
+
+/// The pages that can be shown after login
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Page {
+    Sites,
+    Migration,
+    Sync,
+    Report,
+    Trash,
+}
+
+impl Page {
+    fn title(&self) -> &'static str {
+        match self {
+            Page::Sites => "Sites",
+            Page::Migration => "Migration",
+            Page::Sync => "Sync",
+            Page::Report => "Report",
+            Page::Trash => "Trash",
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Page::Sites => "fa-solid fa-key",
+            Page::Migration => "fa-solid fa-right-left",
+            Page::Sync => "fa-solid fa-rotate",
+            Page::Report => "fa-solid fa-shield-halved",
+            Page::Trash => "fa-solid fa-trash-can",
+        }
+    }
+}
+
+const PAGES: [Page; 5] = [
+    Page::Sites,
+    Page::Migration,
+    Page::Sync,
+    Page::Report,
+    Page::Trash,
+];
+
+#[component]
+pub fn Navigation(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let page = use_context::<RwPage>(cx).unwrap().0;
+
+    view! { cx,
+        <div class="my-5 text-center">
+            <h1 class="display-4 text-light">{login_data().unwrap().name}"'s Password Store"</h1>
+        </div>
+
+        <ul class="nav nav-pills justify-content-center mb-3">
+            {PAGES.into_iter().map(|p| view! { cx,
+                <li class="nav-item">
+                    <a
+                        href="#"
+                        class=move || if page() == p {"nav-link active text-bg-override"} else {"nav-link text-light"}
+                        on:click=move |ev| {
+                            ev.prevent_default();
+                            page.set(p);
+                        }
+                    >
+                        <i class=p.icon() />" "{p.title()}
+                    </a>
+                </li>
+            })
+            .collect::<Vec<_>>()}
+        </ul>
+    }
+}
@@ -0,0 +1,284 @@
+//! Password requirements of sites in Apple's `passwordrules` syntax
+//! (https://developer.apple.com/password-rules/), e.g.
+//! `minlength: 8; maxlength: 16; required: lower; required: upper, digit; allowed: [-_.];`
+
+use std::{collections::BTreeSet, error::Error};
+
+use mpw::masterkey::MasterKey;
+
+use crate::entropy::TemplateStrength;
+
+// number of counters tried per password type when looking for a compliant password
+const SUGGESTION_COUNTERS: i32 = 20;
+
+const SPECIAL: &str = "-~!@#$%^&*_+=`|(){}[:;\"'<>,.? ]";
+
+/// Index of the `]` closing a custom character class (a `]` right after the `[` is a member)
+fn class_end(class: &str) -> Option<usize> {
+    let skip = usize::from(class.starts_with(']'));
+    class[skip..].find(']').map(|end| end + skip)
+}
+
+/// Split rules at the semicolons that are not members of a custom character class
+fn split_rules(rules: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut idx = 0;
+    while let Some(c) = rules[idx..].chars().next() {
+        match c {
+            '[' => match class_end(&rules[idx + 1..]) {
+                Some(end) => idx += end + 1,
+                None => break,
+            },
+            ';' => {
+                parts.push(&rules[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+        idx += c.len_utf8();
+    }
+    parts.push(&rules[start..]);
+    parts
+}
+
+/// A set of characters, optionally including any unicode character
+#[derive(Clone, Debug, Default, PartialEq)]
+struct CharSet {
+    chars: BTreeSet<char>,
+    unicode: bool,
+}
+
+impl CharSet {
+    fn contains(&self, c: char) -> bool {
+        self.unicode || self.chars.contains(&c)
+    }
+
+    fn extend(&mut self, other: &CharSet) {
+        self.chars.extend(other.chars.iter().copied());
+        self.unicode |= other.unicode;
+    }
+
+    /// Parse a comma separated list of character classes like `upper, digit, [-_.]`
+    fn parse(value: &str) -> Result<CharSet, Box<dyn Error>> {
+        let mut set = CharSet::default();
+        let mut rest = value.trim();
+        while !rest.is_empty() {
+            if let Some(custom) = rest.strip_prefix('[') {
+                let end = class_end(custom).ok_or_else(|| format!("Missing ']' in '{}'", value))?;
+                set.chars.extend(custom[..end].chars());
+                rest = &custom[end + 1..];
+            } else {
+                let end = rest.find(',').unwrap_or(rest.len());
+                let name = rest[..end].trim();
+                let chars: Box<dyn Iterator<Item = char>> = match name.to_lowercase().as_str() {
+                    "upper" => Box::new('A'..='Z'),
+                    "lower" => Box::new('a'..='z'),
+                    "digit" => Box::new('0'..='9'),
+                    "special" => Box::new(SPECIAL.chars()),
+                    "ascii-printable" => Box::new(' '..='~'),
+                    "unicode" => {
+                        set.unicode = true;
+                        Box::new(std::iter::empty())
+                    }
+                    _ => return Err(format!("Unknown character class '{}'", name).into()),
+                };
+                set.chars.extend(chars);
+                rest = &rest[end..];
+            }
+            rest = rest.trim_start().trim_start_matches(',').trim_start();
+        }
+        Ok(set)
+    }
+}
+
+/// Parsed password requirements of a site
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PasswordRules {
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    max_consecutive: Option<usize>,
+    /// each set must be represented by at least one character
+    required: Vec<CharSet>,
+    allowed: CharSet,
+}
+
+impl PasswordRules {
+    pub fn parse(rules: &str) -> Result<PasswordRules, Box<dyn Error>> {
+        let mut parsed = PasswordRules::default();
+        let mut any_allowed = false;
+
+        for rule in split_rules(rules)
+            .into_iter()
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+        {
+            let (name, value) = rule
+                .split_once(':')
+                .ok_or_else(|| format!("Missing ':' in rule '{}'", rule))?;
+            let value = value.trim();
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("'{}' is not a number", value))
+            };
+            match name.trim().to_lowercase().as_str() {
+                "minlength" => parsed.min_length = Some(number()?),
+                "maxlength" => parsed.max_length = Some(number()?),
+                "max-consecutive" => parsed.max_consecutive = Some(number()?),
+                "required" => {
+                    let set = CharSet::parse(value)?;
+                    parsed.allowed.extend(&set);
+                    parsed.required.push(set);
+                    any_allowed = true;
+                }
+                "allowed" => {
+                    parsed.allowed.extend(&CharSet::parse(value)?);
+                    any_allowed = true;
+                }
+                other => return Err(format!("Unknown rule '{}'", other).into()),
+            }
+        }
+
+        // without explicit character classes, all printable ASCII characters are allowed
+        if !any_allowed {
+            parsed.allowed = CharSet::parse("ascii-printable")?;
+        }
+        Ok(parsed)
+    }
+
+    /// Reasons why a password violates the rules (empty if it complies)
+    pub fn violations(&self, password: &str) -> Vec<String> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+        if let Some(min) = self.min_length.filter(|min| length < *min) {
+            violations.push(format!("shorter than {} characters", min));
+        }
+        if let Some(max) = self.max_length.filter(|max| length > *max) {
+            violations.push(format!("longer than {} characters", max));
+        }
+
+        let forbidden = password
+            .chars()
+            .filter(|c| !self.allowed.contains(*c))
+            .collect::<BTreeSet<_>>();
+        if !forbidden.is_empty() {
+            let forbidden = forbidden.into_iter().collect::<String>();
+            violations.push(format!("contains forbidden characters '{}'", forbidden));
+        }
+
+        let missing = self
+            .required
+            .iter()
+            .filter(|set| !password.chars().any(|c| set.contains(c)))
+            .count();
+        if missing > 0 {
+            violations.push(format!("misses {} required character classes", missing));
+        }
+
+        if let Some(max) = self.max_consecutive {
+            let mut longest_run = 0;
+            let mut run = 0;
+            let mut previous = None;
+            for c in password.chars() {
+                run = if previous == Some(c) { run + 1 } else { 1 };
+                longest_run = longest_run.max(run);
+                previous = Some(c);
+            }
+            if longest_run > max {
+                violations.push(format!(
+                    "repeats a character more than {} times in a row",
+                    max
+                ));
+            }
+        }
+        violations
+    }
+
+    /// Strongest password type (and lowest counter from `min_counter` on) complying with the rules
+    pub fn suggest(
+        &self,
+        masterkey: &MasterKey,
+        site_name: &str,
+        min_counter: i32,
+    ) -> Option<(&'static str, i32)> {
+        let mut types = TemplateStrength::all();
+        types.sort_by(|t1, t2| t2.bits.total_cmp(&t1.bits));
+        types.into_iter().find_map(|t| {
+            (min_counter..min_counter + SUGGESTION_COUNTERS)
+                .find(|counter| {
+                    let password = masterkey.generate_password(site_name, &t.name.into(), *counter);
+                    self.violations(&password).is_empty()
+                })
+                .map(|counter| (t.name, counter))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn semicolons_and_brackets_in_custom_classes() {
+        let rules = PasswordRules::parse("required: [;]; allowed: []-], lower").unwrap();
+        assert_eq!(rules.required.len(), 1);
+        assert!(rules.required[0].contains(';'));
+        assert!(rules.violations("abc;]-").is_empty());
+        assert_eq!(
+            rules.violations("abc;!"),
+            vec!["contains forbidden characters '!'"]
+        );
+        assert!(PasswordRules::parse("allowed: [abc; minlength: 3").is_err());
+    }
+
+    #[test]
+    fn violations_of_lengths_classes_and_repetitions() {
+        let rules = PasswordRules::parse(
+            "minlength: 8; maxlength: 12; max-consecutive: 2; required: lower; required: upper, digit; allowed: [-_];",
+        )
+        .unwrap();
+        assert!(rules.violations("abcd-EFG_1").is_empty());
+        assert_eq!(
+            rules.violations("abc"),
+            vec![
+                "shorter than 8 characters",
+                "misses 1 required character classes"
+            ]
+        );
+        assert_eq!(
+            rules.violations("abcdefghijk1m"),
+            vec!["longer than 12 characters"]
+        );
+        assert_eq!(
+            rules.violations("abcdefg!1"),
+            vec!["contains forbidden characters '!'"]
+        );
+        assert_eq!(
+            rules.violations("aaabbbc1"),
+            vec!["repeats a character more than 2 times in a row"]
+        );
+    }
+
+    #[test]
+    fn without_character_classes_printable_ascii_is_allowed() {
+        let rules = PasswordRules::parse("minlength: 4").unwrap();
+        assert!(rules.violations("a b~").is_empty());
+        assert_eq!(
+            rules.violations("äbcd"),
+            vec!["contains forbidden characters 'ä'"]
+        );
+        assert!(PasswordRules::parse("allowed: unicode")
+            .unwrap()
+            .violations("äbcd")
+            .is_empty());
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(PasswordRules::parse("minlength 8").is_err());
+        assert!(PasswordRules::parse("minlength: eight").is_err());
+        assert!(PasswordRules::parse("required: emoji").is_err());
+        assert!(PasswordRules::parse("maximum: 8").is_err());
+    }
+}
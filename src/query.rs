@@ -0,0 +1,546 @@
+//! Query language of the site filter
+//!
+//! A query combines free text with field predicates, e.g.
+//! `tag:work type:PIN counter:>1 modified:<2025-01-01`. Terms are combined with `AND` (implicit
+//! between adjacent terms) and `OR` (or `|`), negated with `-` or `NOT`, and grouped with
+//! parentheses. Values containing spaces can be quoted: `notes:"recovery codes"`, and
+//! `is:favorite` matches favorite sites.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{groups::is_in_group, search::text_matches, sites::Site, storage::MS_PER_DAY};
+
+/// A query stored under a name for quick access
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+/// Syntax error in a query
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    /// character offset of the error in the query
+    pub position: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+fn error<T>(message: impl Into<String>, position: usize) -> Result<T, QueryError> {
+    Err(QueryError {
+        message: message.into(),
+        position,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Split a leading comparison operator off a value (no operator means equality)
+    fn split(value: &str) -> (Comparison, &str) {
+        let operators = [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("=", Comparison::Equal),
+        ];
+        operators
+            .iter()
+            .find_map(|(op, cmp)| value.strip_prefix(op).map(|rest| (*cmp, rest)))
+            .unwrap_or((Comparison::Equal, value))
+    }
+
+    fn holds<T: PartialOrd>(&self, left: T, right: T) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Predicate {
+    /// free text, matched against all fields (see `search::text_matches`)
+    Text(String),
+    Name(String),
+    Label(String),
+    Login(String),
+    Url(String),
+    Notes(String),
+    Tag(String),
+    /// group including its subgroups
+    Group(String),
+    Alias(String),
+    Type(String),
+    Counter(Comparison, i32),
+    Favorite,
+    Id(String),
+    /// day (milliseconds since the epoch at midnight UTC)
+    Modified(Comparison, u64),
+}
+
+/// Field names of predicates
+///
+/// Other words containing a colon are free text, e.g. URLs, ports (`example.com:8080`) or times
+/// (`10:30`).
+const FIELDS: &[&str] = &[
+    "name", "site", "label", "login", "user", "url", "notes", "note", "tag", "group", "alias",
+    "type", "id", "is", "counter", "modified",
+];
+
+impl Predicate {
+    fn parse(word: &str, position: usize) -> Result<Predicate, QueryError> {
+        let Some((field, value)) = word
+            .split_once(':')
+            .filter(|(field, _)| FIELDS.contains(&field.to_lowercase().as_str()))
+        else {
+            return Ok(Predicate::Text(word.to_string()));
+        };
+        if value.is_empty() {
+            return error(format!("Missing value for '{}:'", field), position);
+        }
+
+        let value_position = position + field.chars().count() + 1;
+        let predicate = match field.to_lowercase().as_str() {
+            "name" | "site" => Predicate::Name(value.to_lowercase()),
+            "label" => Predicate::Label(value.to_lowercase()),
+            "login" | "user" => Predicate::Login(value.to_lowercase()),
+            "url" => Predicate::Url(value.to_lowercase()),
+            "notes" | "note" => Predicate::Notes(value.to_lowercase()),
+            "tag" => Predicate::Tag(value.to_lowercase()),
+            "group" => Predicate::Group(value.to_string()),
+            "alias" => Predicate::Alias(value.to_string()),
+            "type" => Predicate::Type(value.to_lowercase()),
+            "id" => Predicate::Id(value.to_string()),
+            "is" if value.eq_ignore_ascii_case("favorite") => Predicate::Favorite,
+            "is" => return error(format!("Unknown property '{}'", value), value_position),
+            "counter" => {
+                let (cmp, number) = Comparison::split(value);
+                match number.parse::<i32>() {
+                    Ok(number) => Predicate::Counter(cmp, number),
+                    Err(_) => {
+                        return error(format!("'{}' is not a number", number), value_position)
+                    }
+                }
+            }
+            "modified" => {
+                let (cmp, date) = Comparison::split(value);
+                match parse_date(date) {
+                    Some(day) => Predicate::Modified(cmp, day),
+                    None => {
+                        return error(
+                            format!("'{}' is not a date (expected YYYY-MM-DD)", date),
+                            value_position,
+                        )
+                    }
+                }
+            }
+            _ => unreachable!("fields are checked against FIELDS"),
+        };
+        Ok(predicate)
+    }
+
+    fn matches(&self, site: &Site) -> bool {
+        let contains = |text: &str, value: &str| text.to_lowercase().contains(value);
+        match self {
+            Predicate::Text(text) => text_matches(site, text),
+            Predicate::Name(value) => contains(&site.site_name, value),
+            Predicate::Label(value) => contains(&site.label, value),
+            Predicate::Login(value) => contains(&site.login_name, value),
+            Predicate::Url(value) => contains(&site.url, value),
+            Predicate::Notes(value) => contains(&site.notes, value),
+            Predicate::Tag(value) => site.tags.iter().any(|t| t.to_lowercase() == *value),
+            Predicate::Group(value) => is_in_group(&site.group, value),
+            Predicate::Alias(value) => site.matches_domain(value),
+            Predicate::Type(value) => site.password_type.to_lowercase() == *value,
+            Predicate::Counter(cmp, number) => cmp.holds(site.counter, *number),
+            Predicate::Favorite => site.favorite,
+            Predicate::Id(id) => site.id == *id,
+            // sites without a modification time count as modified at the epoch
+            Predicate::Modified(cmp, day) => {
+                cmp.holds(site.modified / MS_PER_DAY * MS_PER_DAY, *day)
+            }
+        }
+    }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parse a date in the form `YYYY-MM-DD` into milliseconds since the epoch (midnight UTC)
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    let valid_day = (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day);
+    if year < 1970 || !valid_day {
+        return None;
+    }
+
+    // days since the epoch of a proleptic Gregorian date
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(days as u64 * MS_PER_DAY)
+}
+
+/// A parsed query
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    /// matches every site
+    All,
+    Term(Predicate),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let start = idx;
+        match chars[idx] {
+            c if c.is_whitespace() => idx += 1,
+            '(' => {
+                tokens.push((Token::Open, start));
+                idx += 1;
+            }
+            ')' => {
+                tokens.push((Token::Close, start));
+                idx += 1;
+            }
+            '|' => {
+                tokens.push((Token::Or, start));
+                idx += 1;
+            }
+            '-' | '!' => {
+                tokens.push((Token::Not, start));
+                idx += 1;
+            }
+            _ => {
+                // a word, possibly with quoted parts (e.g. notes:"two words")
+                let mut word = String::new();
+                let mut quote_start = None;
+                while idx < chars.len() {
+                    let c = chars[idx];
+                    if quote_start.is_some() {
+                        if c == '"' {
+                            quote_start = None;
+                        } else {
+                            word.push(c);
+                        }
+                    } else if c == '"' {
+                        quote_start = Some(idx);
+                    } else if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    } else {
+                        word.push(c);
+                    }
+                    idx += 1;
+                }
+                if let Some(quote_start) = quote_start {
+                    return error("Unterminated quote", quote_start);
+                }
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                };
+                tokens.push((token, start));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    idx: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.idx)
+            .map(|(_, pos)| *pos)
+            .unwrap_or(self.end)
+    }
+
+    fn parse_or(&mut self) -> Result<Query, QueryError> {
+        let mut operands = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.idx += 1;
+            operands.push(self.parse_and()?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.pop().unwrap()
+        } else {
+            Query::Or(operands)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Query, QueryError> {
+        let mut operands = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.idx += 1,
+                Some(Token::Word(_)) | Some(Token::Not) | Some(Token::Open) => {}
+                _ => break,
+            }
+            operands.push(self.parse_unary()?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.pop().unwrap()
+        } else {
+            Query::And(operands)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, QueryError> {
+        let position = self.position();
+        let Some(token) = self.peek().cloned() else {
+            return error("Unexpected end of query", position);
+        };
+        self.idx += 1;
+        match token {
+            Token::Not => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Token::Open => {
+                let query = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return error("Missing closing parenthesis", self.position());
+                }
+                self.idx += 1;
+                Ok(query)
+            }
+            Token::Word(word) => Ok(Query::Term(Predicate::parse(&word, position)?)),
+            Token::Close => error("Unexpected ')'", position),
+            Token::And | Token::Or => error("Operator without left operand", position),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, QueryError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(Query::All);
+        }
+
+        let mut parser = Parser {
+            tokens,
+            idx: 0,
+            end: input.chars().count(),
+        };
+        let query = parser.parse_or()?;
+        if parser.idx < parser.tokens.len() {
+            return error("Unexpected ')'", parser.position());
+        }
+        Ok(query)
+    }
+
+    /// Query matching the input as plain free text (used when the input has syntax errors)
+    pub fn text(input: &str) -> Query {
+        let terms = input
+            .split_whitespace()
+            .map(|t| Query::Term(Predicate::Text(t.to_string())))
+            .collect::<Vec<_>>();
+        if terms.is_empty() {
+            Query::All
+        } else {
+            Query::And(terms)
+        }
+    }
+
+    pub fn matches(&self, site: &Site) -> bool {
+        match self {
+            Query::All => true,
+            Query::Term(predicate) => predicate.matches(site),
+            Query::Not(query) => !query.matches(site),
+            Query::And(queries) => queries.iter().all(|q| q.matches(site)),
+            Query::Or(queries) => queries.iter().any(|q| q.matches(site)),
+        }
+    }
+
+    /// Free text terms that are not negated (used for ranking and highlighting)
+    pub fn text_terms(&self) -> Vec<&str> {
+        match self {
+            Query::Term(Predicate::Text(text)) => vec![text.as_str()],
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().flat_map(|q| q.text_terms()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(t: &str) -> Query {
+        Query::Term(Predicate::Text(t.to_string()))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            Query::parse("a b | c AND d").unwrap(),
+            Query::Or(vec![
+                Query::And(vec![text("a"), text("b")]),
+                Query::And(vec![text("c"), text("d")]),
+            ])
+        );
+        assert_eq!(
+            Query::parse("a (b OR c)").unwrap(),
+            Query::And(vec![text("a"), Query::Or(vec![text("b"), text("c")])])
+        );
+    }
+
+    #[test]
+    fn negation_with_minus_and_not() {
+        let negated = Query::Not(Box::new(Query::Term(Predicate::Tag("work".to_string()))));
+        assert_eq!(Query::parse("-tag:work").unwrap(), negated);
+        assert_eq!(Query::parse("NOT tag:Work").unwrap(), negated);
+        assert_eq!(
+            Query::parse("a -b").unwrap(),
+            Query::And(vec![text("a"), Query::Not(Box::new(text("b")))])
+        );
+    }
+
+    #[test]
+    fn quoted_values_and_urls() {
+        assert_eq!(
+            Query::parse("notes:\"Recovery Codes\"").unwrap(),
+            Query::Term(Predicate::Notes("recovery codes".to_string()))
+        );
+        assert_eq!(
+            Query::parse("https://example.com/login").unwrap(),
+            text("https://example.com/login")
+        );
+        assert_eq!(Query::parse("  ").unwrap(), Query::All);
+    }
+
+    #[test]
+    fn only_known_fields_are_predicates() {
+        for word in [
+            "example.com:8080",
+            "10:30",
+            "colour:red",
+            "http://example.com",
+            "a:",
+        ] {
+            assert_eq!(Query::parse(word).unwrap(), text(word));
+        }
+        assert_eq!(
+            Query::parse("Tag:work 10:30").unwrap(),
+            Query::And(vec![
+                Query::Term(Predicate::Tag("work".to_string())),
+                text("10:30")
+            ])
+        );
+        assert!(Query::parse("is:archived").is_err());
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(
+            Query::parse("counter:>=2").unwrap(),
+            Query::Term(Predicate::Counter(Comparison::GreaterOrEqual, 2))
+        );
+        assert_eq!(
+            Query::parse("counter:3").unwrap(),
+            Query::Term(Predicate::Counter(Comparison::Equal, 3))
+        );
+        assert_eq!(
+            Query::parse("modified:<1970-01-02").unwrap(),
+            Query::Term(Predicate::Modified(Comparison::Less, MS_PER_DAY))
+        );
+
+        let site = Site {
+            counter: 2,
+            modified: MS_PER_DAY + 1,
+            ..Default::default()
+        };
+        assert!(Query::parse("counter:>1 counter:<=2")
+            .unwrap()
+            .matches(&site));
+        assert!(!Query::parse("counter:<2").unwrap().matches(&site));
+        assert!(Query::parse("modified:1970-01-02").unwrap().matches(&site));
+    }
+
+    #[test]
+    fn dates_are_validated() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-03-01"), Some(11_017 * MS_PER_DAY));
+        assert!(parse_date("2024-02-29").is_some());
+        assert!(parse_date("2000-02-29").is_some());
+        assert!(parse_date("2023-02-29").is_none());
+        assert!(parse_date("1900-02-29").is_none());
+        assert!(parse_date("2024-04-31").is_none());
+        assert!(parse_date("2024-13-01").is_none());
+        assert!(parse_date("1969-12-31").is_none());
+        assert!(parse_date("yesterday").is_none());
+    }
+
+    #[test]
+    fn errors_report_their_position() {
+        let position = |query: &str| Query::parse(query).unwrap_err().position;
+        assert_eq!(position("(a b"), 4);
+        assert_eq!(position("a )"), 2);
+        assert_eq!(position("OR a"), 0);
+        assert_eq!(position("a -"), 3);
+        assert_eq!(position("notes:\"open"), 6);
+        assert_eq!(position("counter:x"), 8);
+        assert_eq!(position("tag:"), 0);
+    }
+}
@@ -0,0 +1,123 @@
+//! Groups of domains that share one account
+//!
+//! Curated after Apple's shared credentials list
+//! (https://github.com/apple/password-manager-resources/blob/main/quirks/shared-credentials.json).
+
+const RELATED_DOMAINS: &[&[&str]] = &[
+    &["google.com", "youtube.com", "gmail.com", "blogger.com"],
+    &[
+        "live.com",
+        "outlook.com",
+        "hotmail.com",
+        "microsoft.com",
+        "office.com",
+        "skype.com",
+        "xbox.com",
+        "msn.com",
+    ],
+    &["apple.com", "icloud.com"],
+    &["facebook.com", "messenger.com"],
+    &[
+        "amazon.com",
+        "amazon.de",
+        "amazon.co.uk",
+        "amazon.fr",
+        "amazon.it",
+        "amazon.es",
+        "amazon.ca",
+        "amazon.co.jp",
+        "audible.com",
+    ],
+    &["ebay.com", "ebay.de", "ebay.co.uk", "ebay.fr", "ebay.it"],
+    &["paypal.com", "paypal.me"],
+    &[
+        "atlassian.com",
+        "atlassian.net",
+        "bitbucket.org",
+        "trello.com",
+    ],
+    &[
+        "stackoverflow.com",
+        "stackexchange.com",
+        "superuser.com",
+        "serverfault.com",
+        "askubuntu.com",
+    ],
+    &["yahoo.com", "flickr.com", "tumblr.com"],
+    &["steampowered.com", "steamcommunity.com"],
+    &["ubisoft.com", "ubi.com"],
+    &[
+        "wikipedia.org",
+        "wikimedia.org",
+        "wiktionary.org",
+        "wikidata.org",
+    ],
+    &["mozilla.org", "firefox.com"],
+    &[
+        "sony.com",
+        "playstation.com",
+        "sonyentertainmentnetwork.com",
+    ],
+    &["dropbox.com", "getdropbox.com"],
+    &["airbnb.com", "airbnb.de", "airbnb.co.uk", "airbnb.fr"],
+    &["discord.com", "discord.gg", "discordapp.com"],
+    &["twitter.com", "x.com"],
+    &["linkedin.com", "slideshare.net"],
+];
+
+/// Normalize a domain for comparison (lowercase, without a leading "www." and trailing dot)
+pub fn normalize_domain(domain: &str) -> String {
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+    domain
+        .strip_prefix("www.")
+        .map(str::to_string)
+        .unwrap_or(domain)
+}
+
+/// Whether `domain` is `parent` or one of its subdomains
+pub fn is_same_or_subdomain(domain: &str, parent: &str) -> bool {
+    domain == parent
+        || (domain.len() > parent.len()
+            && domain.ends_with(parent)
+            && domain[..domain.len() - parent.len()].ends_with('.'))
+}
+
+/// All domains sharing an account with `domain` (including `domain` itself)
+pub fn related_domains(domain: &str) -> Vec<String> {
+    let domain = normalize_domain(domain);
+    let mut related = RELATED_DOMAINS
+        .iter()
+        .find(|group| group.iter().any(|d| is_same_or_subdomain(&domain, d)))
+        .map(|group| group.iter().map(|d| d.to_string()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if !related.contains(&domain) {
+        related.push(domain);
+    }
+    related
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subdomains_match_their_parent_only() {
+        assert!(is_same_or_subdomain("example.com", "example.com"));
+        assert!(is_same_or_subdomain("login.example.com", "example.com"));
+        assert!(!is_same_or_subdomain("badexample.com", "example.com"));
+        assert!(!is_same_or_subdomain("example.com", "login.example.com"));
+    }
+
+    #[test]
+    fn related_domains_of_grouped_and_ungrouped_domains() {
+        let related = related_domains("WWW.YouTube.com.");
+        assert!(related.contains(&"google.com".to_string()));
+        assert!(related.contains(&"youtube.com".to_string()));
+        // subdomains of a grouped domain belong to the group
+        assert!(related_domains("accounts.google.com").contains(&"gmail.com".to_string()));
+        assert_eq!(
+            related_domains("example.org"),
+            vec!["example.org".to_string()]
+        );
+    }
+}
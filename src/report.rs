@@ -0,0 +1,284 @@
+use std::{collections::BTreeMap, net::IpAddr};
+
+use leptos::*;
+
+use crate::{
+    breach::{breached_sites, BreachCheck},
+    domain::domain_from_url,
+    migration::MigrationStatus,
+    navigation::Page,
+    sites::Site,
+    storage::timestamp,
+    RwBreachFilter, RwLoginData, RwPage, RwSiteFilter, RwStorage,
+};
+
+// templates that are easy to guess
+const WEAK_TYPES: [&str; 3] = ["PIN", "Basic", "Short"];
+
+/// Tag marking sites that only accept short passwords (e.g. a PIN), so a weak template is intended
+const WEAK_ALLOWED_TAG: &str = "pin-only";
+
+/// Key under which site names that likely refer to the same account coincide
+///
+/// Domains are keyed by the first label of their registrable domain, so that e.g. "github.com",
+/// "GitHub" and "git-hub" coincide. IP addresses are kept as a whole.
+fn near_duplicate_key(site_name: &str) -> String {
+    let name = match domain_from_url(site_name) {
+        Some(ip) if ip.parse::<IpAddr>().is_ok() || ip.starts_with('[') => return ip,
+        Some(domain) => domain.split('.').next().unwrap_or_default().to_string(),
+        None => site_name.to_string(),
+    };
+    name.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// Groups of sites whose names differ only slightly (and thus generate different passwords)
+fn near_duplicates(sites: &[Site]) -> Vec<Vec<Site>> {
+    let mut groups: BTreeMap<String, Vec<Site>> = BTreeMap::new();
+    for site in sites {
+        groups
+            .entry(near_duplicate_key(&site.site_name))
+            .or_default()
+            .push(site.clone());
+    }
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+/// Filter query listing exactly the given sites
+fn id_query(sites: &[Site]) -> String {
+    sites
+        .iter()
+        .map(|s| format!("id:\"{}\"", s.id))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// One kind of finding with the affected sites
+#[component]
+fn Finding(
+    cx: Scope,
+    title: &'static str,
+    description: &'static str,
+    // groups of affected sites (each listed in one line)
+    groups: Vec<Vec<Site>>,
+) -> impl IntoView {
+    // from global context
+    let page = use_context::<RwPage>(cx).unwrap().0;
+    let filter = use_context::<RwSiteFilter>(cx).unwrap().0;
+
+    let show_sites = move |query: String| {
+        filter.set(query);
+        page.set(Page::Sites);
+    };
+
+    let count = groups.iter().map(|g| g.len()).sum::<usize>();
+    let all_sites = groups.concat();
+    let badge_class = if count == 0 {
+        "badge text-bg-success"
+    } else {
+        "badge text-bg-warning"
+    };
+
+    view! { cx,
+        <li class="list-group-item">
+            <div class="d-flex justify-content-between align-items-center">
+                <span>
+                    <b>{title}</b>
+                    <br />
+                    <small class="text-muted">{description}</small>
+                </span>
+                <span>
+                    <span class=badge_class>{count}</span>
+                    {(count > 0).then(|| view! { cx,
+                        <button class="btn btn-sm btn-light btn-outline-secondary ms-2" type="button"
+                            on:click=move |_| show_sites(id_query(&all_sites))
+                        >
+                            "Show all"
+                        </button>
+                    })}
+                </span>
+            </div>
+            <ul class="mb-0 mt-1">
+                {groups.into_iter().map(|group| {
+                    let names = group.iter().map(|s| s.display_name().to_string()).collect::<Vec<_>>().join(", ");
+                    let query = id_query(&group);
+                    view! { cx,
+                        <li>
+                            <a href="#" on:click=move |ev| {
+                                ev.prevent_default();
+                                show_sites(query.clone());
+                            }>
+                                {names}
+                            </a>
+                        </li>
+                    }
+                })
+                .collect::<Vec<_>>()}
+            </ul>
+        </li>
+    }
+}
+
+#[component]
+pub fn Report(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let masterkey = move || login_data().unwrap().masterkey;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+    let breach_filter = use_context::<RwBreachFilter>(cx).unwrap().0;
+
+    let sites = store()
+        .decrypt_sites(&login_name(), &storage_password())
+        .unwrap_or(Vec::new());
+    let settings = store()
+        .decrypt_settings(&login_name(), &storage_password())
+        .unwrap_or_default();
+
+    // checked again whenever the filter is loaded or counters are bumped
+    let breached = move || {
+        let sites = store()
+            .decrypt_sites(&login_name(), &storage_password())
+            .unwrap_or(Vec::new());
+        breach_filter().map(|filter| breached_sites(&filter, &masterkey(), &sites))
+    };
+
+    let single = |sites: Vec<Site>| sites.into_iter().map(|s| vec![s]).collect::<Vec<_>>();
+    let weak = sites
+        .iter()
+        .filter(|s| WEAK_TYPES.contains(&s.password_type.as_str()))
+        .filter(|s| {
+            !s.tags
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(WEAK_ALLOWED_TAG))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let overdue = sites
+        .iter()
+        .filter(|s| settings.rotation.overdue_days(s, timestamp()).is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+    let pending_migration = sites
+        .iter()
+        .filter(|s| {
+            s.migration
+                .map_or(false, |m| m != MigrationStatus::Verified)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let duplicates = near_duplicates(&sites);
+
+    let mut distribution: BTreeMap<String, usize> = BTreeMap::new();
+    for site in &sites {
+        *distribution.entry(site.password_type.clone()).or_default() += 1;
+    }
+    let total = sites.len().max(1);
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-header text-bg-secondary text-bg-override">
+                <span class="fs-4">"Security Report"</span>
+            </div>
+            <ul class="list-group list-group-flush">
+                <Finding
+                    title="Weak password types"
+                    description="Sites using the PIN, Basic or Short type without the \"pin-only\" tag"
+                    groups=single(weak)
+                />
+                {move || breached().map(|breached| view! { cx,
+                    <Finding
+                        title="Breached passwords"
+                        description="Generated passwords found in the loaded breach filter"
+                        groups=single(breached)
+                    />
+                })}
+                <Finding
+                    title="Overdue rotations"
+                    description="Passwords older than their rotation policy"
+                    groups=single(overdue)
+                />
+                <Finding
+                    title="Near-duplicate sites"
+                    description="Similar site names that generate different passwords for probably the same account"
+                    groups=duplicates
+                />
+                <Finding
+                    title="Pending migration"
+                    description="Imported sites that do not use their generated password, yet"
+                    groups=single(pending_migration)
+                />
+            </ul>
+        </div>
+
+        <BreachCheck />
+
+        <div class="card mb-3 border-dark">
+            <div class="card-header">
+                <span class="fs-4">"Password Types"</span>
+            </div>
+            <div class="card-body text-bg-light">
+                {distribution.into_iter().map(|(password_type, count)| view! { cx,
+                    <div class="row align-items-center mb-1">
+                        <span class="col-3">{password_type}</span>
+                        <div class="col-9">
+                            <div class="progress">
+                                <div class="progress-bar bg-secondary" style=format!("width: {}%", 100 * count / total)>
+                                    {count}
+                                </div>
+                            </div>
+                        </div>
+                    </div>
+                })
+                .collect::<Vec<_>>()}
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn near_duplicates_ignore_scheme_subdomain_suffix_and_punctuation() {
+        let key = near_duplicate_key;
+        assert_eq!(key("github.com"), "github");
+        assert_eq!(key("https://www.GitHub.com/login"), "github");
+        assert_eq!(key("Git-Hub"), "github");
+        assert_eq!(key("shop.example.co.uk"), "example");
+        assert_eq!(key("192.168.1.1"), "192.168.1.1");
+        assert_ne!(key("192.168.1.1"), key("192.168.1.2"));
+    }
+
+    #[test]
+    fn near_duplicates_are_grouped() {
+        let site = |name: &str| Site {
+            site_name: name.to_string(),
+            ..Default::default()
+        };
+        let sites = [
+            "github.com",
+            "GitHub",
+            "gitlab.com",
+            "example.org",
+            "login.example.net",
+        ]
+        .map(site);
+        let groups = near_duplicates(&sites)
+            .into_iter()
+            .map(|g| g.into_iter().map(|s| s.site_name).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                vec!["example.org", "login.example.net"],
+                vec!["github.com", "GitHub"],
+            ]
+        );
+    }
+}
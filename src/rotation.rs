@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{sites::Site, storage::MS_PER_DAY, RwLoginData, RwStorage};
+
+/// How often passwords should be rotated (0 days means never)
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct RotationSettings {
+    /// policy for sites without a policy of their own or of one of their tags
+    #[serde(default)]
+    pub default_days: u32,
+    /// policies per tag (lowercase)
+    #[serde(default)]
+    pub tag_days: BTreeMap<String, u32>,
+}
+
+impl RotationSettings {
+    /// Rotation interval of a site in days, `None` if it needs no rotation
+    ///
+    /// A site's own policy takes precedence, then the strictest policy of its tags, then the
+    /// default.
+    pub fn policy_days(&self, site: &Site) -> Option<u32> {
+        let days = site.rotation_days.unwrap_or_else(|| {
+            site.tags
+                .iter()
+                .filter_map(|t| self.tag_days.get(&t.to_lowercase()))
+                .filter(|d| **d > 0)
+                .min()
+                .copied()
+                .unwrap_or(self.default_days)
+        });
+        (days > 0).then_some(days)
+    }
+
+    /// Days by which the rotation of a site is overdue, `None` if it is not overdue
+    pub fn overdue_days(&self, site: &Site, now: u64) -> Option<u64> {
+        let days = self.policy_days(site)? as u64;
+        let age = password_age_days(site, now)?;
+        (age > days).then(|| age - days)
+    }
+}
+
+/// Days since the password of a site last changed, `None` if unknown
+pub fn password_age_days(site: &Site, now: u64) -> Option<u64> {
+    // sites stored before rotation was tracked fall back to their last modification
+    let since = if site.rotated > 0 {
+        site.rotated
+    } else {
+        site.modified
+    };
+    (since > 0).then(|| now.saturating_sub(since) / MS_PER_DAY)
+}
+
+#[component]
+pub fn RotationPolicies(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // signals
+    let new_tag = create_rw_signal(cx, "".to_string());
+    let new_days = create_rw_signal(cx, 90u32);
+
+    // derived signals
+    let settings = move || {
+        store()
+            .decrypt_settings(&login_name(), &storage_password())
+            .unwrap_or_default()
+    };
+    let rotation = move || settings().rotation;
+
+    let update_rotation = move |f: &dyn Fn(&mut RotationSettings)| {
+        let mut settings = settings();
+        f(&mut settings.rotation);
+        store.update(|data| data.store_settings(&login_name(), &storage_password(), &settings));
+    };
+    let add_tag_policy = move |_| {
+        let tag = new_tag().trim().to_lowercase();
+        if !tag.is_empty() {
+            update_rotation(&|r| {
+                r.tag_days.insert(tag.clone(), new_days());
+            });
+            new_tag.set("".to_string());
+        }
+    };
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-header">
+                <span class="fs-4">"Rotation Policies"</span>
+            </div>
+            <div class="card-body text-bg-light">
+                <div class="input-group mb-3">
+                    <span class="input-group-text">"Rotate all passwords every"</span>
+                    <input class="form-control" type="number" min="0"
+                        on:change=move |ev| {
+                            let days = event_target_value(&ev).parse().unwrap_or(0);
+                            update_rotation(&|r| r.default_days = days);
+                        }
+                        prop:value=move || rotation().default_days
+                    />
+                    <span class="input-group-text">"days"</span>
+                </div>
+
+                <label>"Per tag"</label>
+                <ul class="list-group mb-2">
+                    {move || rotation().tag_days.into_iter().map(|(tag, days)| {
+                        let removed_tag = tag.clone();
+                        view! { cx,
+                            <li class="list-group-item d-flex justify-content-between align-items-center">
+                                <span>
+                                    <span class="badge text-bg-light border me-2">{tag}</span>
+                                    {format!("every {} days", days)}
+                                </span>
+                                <button class="btn btn-sm btn-light btn-outline-danger" type="button"
+                                    on:click=move |_| update_rotation(&|r| {
+                                        r.tag_days.remove(&removed_tag);
+                                    })
+                                >
+                                    <i class="fa-solid fa-xmark" />
+                                </button>
+                            </li>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+                </ul>
+                <div class="input-group">
+                    <input class="form-control" type="text" placeholder="Tag"
+                        on:input=move |ev| new_tag.set(event_target_value(&ev))
+                        prop:value=new_tag
+                    />
+                    <input class="form-control" type="number" min="1"
+                        on:change=move |ev| new_days.set(event_target_value(&ev).parse().unwrap_or(90))
+                        prop:value=new_days
+                    />
+                    <span class="input-group-text">"days"</span>
+                    <button class="btn btn-light btn-outline-secondary" type="button"
+                        disabled=move || new_tag().trim().is_empty()
+                        on:click=add_tag_policy
+                    >
+                        <i class="fa-solid fa-plus" />" Add"
+                    </button>
+                </div>
+                <small class="text-muted">
+                    "Policies set on a site override those of its tags, the strictest tag policy overrides the default."
+                </small>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> RotationSettings {
+        RotationSettings {
+            default_days: 365,
+            tag_days: [("work".to_string(), 90), ("bank".to_string(), 30)]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn site_policy_beats_tag_policies_beat_default() {
+        let site = |rotation_days: Option<u32>, tags: &[&str]| Site {
+            rotation_days,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+        let settings = settings();
+        assert_eq!(settings.policy_days(&site(None, &[])), Some(365));
+        assert_eq!(settings.policy_days(&site(None, &["Work"])), Some(90));
+        // the strictest tag policy applies
+        assert_eq!(
+            settings.policy_days(&site(None, &["work", "bank"])),
+            Some(30)
+        );
+        assert_eq!(settings.policy_days(&site(Some(7), &["bank"])), Some(7));
+        // a site's own policy of 0 days turns rotation off
+        assert_eq!(settings.policy_days(&site(Some(0), &["bank"])), None);
+        assert_eq!(
+            RotationSettings::default().policy_days(&site(None, &[])),
+            None
+        );
+    }
+
+    #[test]
+    fn overdue_days_count_from_the_last_password_change() {
+        let now = 100 * MS_PER_DAY;
+        let site = Site {
+            tags: vec!["bank".to_string()],
+            modified: 10 * MS_PER_DAY,
+            rotated: 60 * MS_PER_DAY,
+            ..Default::default()
+        };
+        assert_eq!(password_age_days(&site, now), Some(40));
+        assert_eq!(settings().overdue_days(&site, now), Some(10));
+
+        let unchanged = Site { rotated: 0, ..site };
+        assert_eq!(password_age_days(&unchanged, now), Some(90));
+        assert_eq!(password_age_days(&Site::default(), now), None);
+        assert_eq!(settings().overdue_days(&Site::default(), now), None);
+    }
+}
@@ -0,0 +1,291 @@
+//! Fuzzy, ranked search over the sites
+
+use leptos::*;
+
+use crate::{domain::domain_from_url, query::Query, sites::Site};
+
+// points per matched character and bonuses for well-placed matches
+const MATCH_SCORE: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 8;
+const WORD_START_BONUS: i64 = 12;
+const GAP_PENALTY: i64 = 1;
+
+// score of a pasted URL matching a site by its domain
+const DOMAIN_MATCH_SCORE: i64 = 10_000;
+
+// sites changed within the last weeks rank slightly higher
+const MS_PER_WEEK: u64 = 7 * 24 * 60 * 60 * 1000;
+const RECENCY_BONUS: i64 = 24;
+
+/// Characters of a text with their lowercase form (one char each, so indices stay aligned)
+fn lowercase_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+fn is_word_start(chars: &[char], idx: usize) -> bool {
+    idx == 0 || !chars[idx - 1].is_alphanumeric()
+}
+
+/// Greedily match `pattern` as a subsequence of `text`, starting at `start`
+fn match_from(pattern: &[char], text: &[char], start: usize) -> Option<(i64, Vec<usize>)> {
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut score = 0;
+    let mut idx = start;
+    for &p in pattern {
+        let found = (idx..text.len()).find(|&i| text[i] == p)?;
+        score += MATCH_SCORE;
+        match positions.last() {
+            Some(&last) if found == last + 1 => score += CONSECUTIVE_BONUS,
+            Some(&last) => score -= GAP_PENALTY * (found - last - 1) as i64,
+            None => {}
+        }
+        if is_word_start(text, found) {
+            score += WORD_START_BONUS;
+        }
+        positions.push(found);
+        idx = found + 1;
+    }
+    Some((score, positions))
+}
+
+/// Best fuzzy match of a single search term in `text` (case-insensitive)
+///
+/// Returns the score and the character positions of `text` that matched.
+pub fn fuzzy_match(term: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern = lowercase_chars(term);
+    let text = lowercase_chars(text);
+    let first = *pattern.first()?;
+    (0..text.len())
+        .filter(|&i| text[i] == first)
+        .filter_map(|i| match_from(&pattern, &text, i))
+        .max_by_key(|(score, _)| *score)
+}
+
+/// Best occurrence of a single search term as a substring of `text` (case-insensitive)
+///
+/// Scored like a fuzzy match without gaps, so that both kinds of matches are comparable.
+pub fn substring_match(term: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern = lowercase_chars(term);
+    let text = lowercase_chars(text);
+    if pattern.is_empty() || pattern.len() > text.len() {
+        return None;
+    }
+    let len = pattern.len();
+    (0..=text.len() - len)
+        .filter(|&i| text[i..i + len] == pattern[..])
+        .map(|i| {
+            let mut score = MATCH_SCORE * len as i64 + CONSECUTIVE_BONUS * (len - 1) as i64;
+            if is_word_start(&text, i) {
+                score += WORD_START_BONUS;
+            }
+            (score, (i..i + len).collect())
+        })
+        .max_by_key(|(score, _)| *score)
+}
+
+/// How search terms are matched against a field
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Matching {
+    /// the characters of the term in order, possibly with gaps (for short names)
+    Fuzzy,
+    /// the term as a whole (for longer free text, where fuzzy matches are mostly noise)
+    Substring,
+}
+
+impl Matching {
+    pub fn find(self, term: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+        match self {
+            Matching::Fuzzy => fuzzy_match(term, text),
+            Matching::Substring => substring_match(term, text),
+        }
+    }
+}
+
+/// Searchable fields of a site with the weight of a match in them
+fn weighted_fields(site: &Site) -> Vec<(&str, i64, Matching)> {
+    let mut fields = vec![
+        (site.site_name.as_str(), 4, Matching::Fuzzy),
+        (site.label.as_str(), 4, Matching::Fuzzy),
+    ];
+    fields.extend(
+        site.aliases
+            .iter()
+            .map(|a| (a.as_str(), 3, Matching::Fuzzy)),
+    );
+    fields.extend(site.tags.iter().map(|t| (t.as_str(), 3, Matching::Fuzzy)));
+    fields.push((site.login_name.as_str(), 2, Matching::Substring));
+    fields.push((site.url.as_str(), 2, Matching::Substring));
+    fields.push((site.notes.as_str(), 1, Matching::Substring));
+    fields
+}
+
+/// Match quality of a free text term for a site, `None` if it is not found in any field
+///
+/// A pasted URL matching the site by its domain beats any fuzzy match.
+fn term_score(site: &Site, term: &str) -> Option<i64> {
+    if domain_from_url(term).map_or(false, |d| site.matches_domain(&d)) {
+        return Some(DOMAIN_MATCH_SCORE);
+    }
+    weighted_fields(site)
+        .iter()
+        .filter_map(|(text, weight, matching)| matching.find(term, text).map(|(s, _)| s * weight))
+        .max()
+}
+
+/// Whether a free text term matches any field of a site
+///
+/// Names, labels, aliases and tags are matched fuzzily, the other fields need the whole term.
+pub fn text_matches(site: &Site, term: &str) -> bool {
+    term_score(site, term).is_some()
+}
+
+fn recency_bonus(modified: u64, now: u64) -> i64 {
+    if modified == 0 {
+        return 0;
+    }
+    let weeks = now.saturating_sub(modified) / MS_PER_WEEK;
+    RECENCY_BONUS / (1 + weeks as i64)
+}
+
+/// Sites matching `query`, best matches first
+///
+/// Sites are ranked by how well they match the query's free text terms. Without any free text
+/// the matching sites are returned in their original order.
+pub fn search(sites: Vec<Site>, query: &Query, now: u64) -> Vec<Site> {
+    let terms = query.text_terms();
+    if terms.is_empty() {
+        return sites.into_iter().filter(|s| query.matches(s)).collect();
+    }
+
+    let mut ranked = sites
+        .into_iter()
+        .filter(|site| query.matches(site))
+        .map(|site| {
+            // terms in alternatives may not match, they just do not add to the score
+            let score = terms
+                .iter()
+                .filter_map(|term| term_score(&site, term))
+                .sum::<i64>();
+            (score + recency_bonus(site.modified, now), site)
+        })
+        .collect::<Vec<_>>();
+    // stable sort keeps the alphabetical order among equally good matches
+    ranked.sort_by(|(s1, _), (s2, _)| s2.cmp(s1));
+    ranked.into_iter().map(|(_, site)| site).collect()
+}
+
+/// Which characters of `text` match any free text term of `query`
+fn marked_chars(text: &str, query: &str, matching: Matching) -> Vec<bool> {
+    let query = Query::parse(query).unwrap_or_else(|_| Query::text(query));
+    let mut marked = vec![false; text.chars().count()];
+    for term in query.text_terms() {
+        if let Some((_, positions)) = matching.find(term, text) {
+            positions.into_iter().for_each(|p| marked[p] = true);
+        }
+    }
+    marked
+}
+
+/// Whether any free text term of `query` matches `text`
+pub fn has_highlights(text: &str, query: &str, matching: Matching) -> bool {
+    marked_chars(text, query, matching).contains(&true)
+}
+
+/// `text` with the characters matching any term of `query` wrapped in `<mark>`
+pub fn highlighted(cx: Scope, text: &str, query: &str, matching: Matching) -> View {
+    let marked = marked_chars(text, query, matching);
+
+    // group runs of equally marked characters
+    let mut runs: Vec<(bool, String)> = Vec::new();
+    for (c, mark) in text.chars().zip(marked) {
+        match runs.last_mut() {
+            Some((m, run)) if *m == mark => run.push(c),
+            _ => runs.push((mark, c.to_string())),
+        }
+    }
+
+    runs.into_iter()
+        .map(|(mark, run)| {
+            if mark {
+                view! { cx, <mark class="p-0">{run}</mark> }.into_view(cx)
+            } else {
+                run.into_view(cx)
+            }
+        })
+        .collect::<Vec<_>>()
+        .into_view(cx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match_prefers_word_starts_and_consecutive_characters() {
+        let (_, positions) = fuzzy_match("gh", "github.com").unwrap();
+        assert_eq!(positions, vec![0, 3]);
+        let (word_start, _) = fuzzy_match("hub", "git hub").unwrap();
+        let (inside, _) = fuzzy_match("hub", "github").unwrap();
+        assert!(word_start > inside);
+        assert!(fuzzy_match("bg", "github").is_none());
+    }
+
+    #[test]
+    fn substring_match_needs_the_whole_term() {
+        assert_eq!(
+            substring_match("CODES", "recovery codes").map(|(_, p)| p),
+            Some((9..14).collect())
+        );
+        assert!(substring_match("rcvry", "recovery codes").is_none());
+        assert!(substring_match("", "recovery codes").is_none());
+    }
+
+    #[test]
+    fn notes_and_logins_are_not_matched_fuzzily() {
+        let site = Site {
+            site_name: "example.com".to_string(),
+            login_name: "jane.doe@mail.com".to_string(),
+            notes: "security question: first pet".to_string(),
+            ..Default::default()
+        };
+        assert!(text_matches(&site, "exmpl"));
+        assert!(text_matches(&site, "jane.doe"));
+        assert!(text_matches(&site, "first pet"));
+        assert!(!text_matches(&site, "scrtyq"));
+        assert!(!text_matches(&site, "jdm"));
+    }
+
+    #[test]
+    fn search_ranks_name_matches_above_notes_matches() {
+        let site = |name: &str, notes: &str| Site {
+            site_name: name.to_string(),
+            notes: notes.to_string(),
+            ..Default::default()
+        };
+        let sites = vec![
+            site("example.com", "shop account"),
+            site("shop.example.org", ""),
+            site("unrelated.net", "nothing"),
+        ];
+        let query = Query::parse("shop").unwrap();
+        let names = search(sites, &query, 0)
+            .into_iter()
+            .map(|s| s.site_name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["shop.example.org", "example.com"]);
+    }
+
+    #[test]
+    fn highlights_only_free_text_terms() {
+        assert!(has_highlights(
+            "example.com",
+            "exa tag:work",
+            Matching::Fuzzy
+        ));
+        assert!(!has_highlights("work", "tag:work", Matching::Fuzzy));
+        assert!(!has_highlights("example.com", "-exa", Matching::Fuzzy));
+        assert!(!has_highlights("my notes", "mnts", Matching::Substring));
+    }
+}
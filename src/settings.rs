@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    query::SavedSearch, rotation::RotationSettings, sites::SortOrder, sync::SyncSettings,
+    trash::TrashSettings, webdav::WebDavSettings,
+};
+
+/// Per-user settings, stored encrypted next to the user's sites
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub sync: SyncSettings,
+    #[serde(default)]
+    pub webdav: WebDavSettings,
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+    // quick-access view listing only favorite sites
+    #[serde(default)]
+    pub favorites_only: bool,
+    #[serde(default)]
+    pub trash: TrashSettings,
+    #[serde(default)]
+    pub rotation: RotationSettings,
+}
@@ -1,13 +1,222 @@
 use leptos::*;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::{RwLoginData, RwStorage};
+use crate::{
+    backup::Backup,
+    bulk::BulkActions,
+    domain::domain_from_url,
+    entropy::TemplateStrengthInfo,
+    groups::{all_groups, group_sections, is_in_group, normalize_group},
+    history::{HistoryEntry, PasswordHistory},
+    known_rules::known_rules,
+    migration::MigrationStatus,
+    password_rules::PasswordRules,
+    query::{Query, SavedSearch},
+    related_domains::{is_same_or_subdomain, normalize_domain, related_domains},
+    rotation::{password_age_days, RotationPolicies},
+    search::{has_highlights, highlighted, search, Matching},
+    storage::timestamp,
+    trash::trash_with_undo,
+    RwBreachFilter, RwLoginData, RwSiteFilter, RwStorage,
+};
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
 pub struct Site {
+    // stable identifier for matching copies of a site across devices
+    #[serde(default)]
+    pub id: String,
+    // number of changes made to the site, used to decide which copy is newer when merging
+    #[serde(default)]
+    pub clock: u64,
+    // tombstone of a deleted site, kept so that merging does not resurrect it
+    #[serde(default)]
+    pub deleted: bool,
+    // milliseconds since the epoch when the site was moved to the trash
+    #[serde(default)]
+    pub trashed: Option<u64>,
+    // the exact string the password is generated for
     pub site_name: String,
+    // optional name to display instead of the site name (does not influence the password)
+    #[serde(default)]
+    pub label: String,
+    // further domains using the same account
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    // pinned to the top of the site list
+    #[serde(default)]
+    pub favorite: bool,
+    // group path with subgroups separated by '/' (empty for ungrouped sites)
+    #[serde(default)]
+    pub group: String,
+    // free-form tags for grouping and searching
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub counter: i32,
     pub password_type: String,
+    // milliseconds since the epoch of the last change (0 for sites stored before it was tracked)
+    #[serde(default)]
+    pub modified: u64,
+    #[serde(default)]
+    pub login_name: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub notes: String,
+    // milliseconds since the epoch of the last time the password was revealed or copied
+    #[serde(default)]
+    pub last_used: u64,
+    // number of times the password was revealed or copied
+    #[serde(default)]
+    pub use_count: u32,
+    // milliseconds since the epoch when the generated password last changed (0 if unknown)
+    #[serde(default)]
+    pub rotated: u64,
+    // password requirements of the site in Apple's passwordrules syntax
+    #[serde(default)]
+    pub rules: String,
+    // rotation interval in days overriding the policies of the tags and the default (0 for never)
+    #[serde(default)]
+    pub rotation_days: Option<u32>,
+    // previous configurations, oldest first
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+    // progress of moving a site imported from another password manager onto its generated password
+    #[serde(default)]
+    pub migration: Option<MigrationStatus>,
+}
+
+impl Site {
+    pub fn new_id() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    /// ID for sites stored before IDs were introduced
+    ///
+    /// It is derived from the site name so that all devices assign the same ID.
+    pub fn legacy_id(site_name: &str) -> String {
+        format!("site:{}", site_name)
+    }
+
+    /// Name shown to the user: the label if set, otherwise the site name
+    pub fn display_name(&self) -> &str {
+        if self.label.is_empty() {
+            &self.site_name
+        } else {
+            &self.label
+        }
+    }
+
+    /// Whether this site holds the account for `domain`
+    ///
+    /// This is the case if `domain` (or a domain it is a subdomain of) is the site name, one of
+    /// its aliases, or shares accounts with one of them according to the bundled related domains.
+    pub fn matches_domain(&self, domain: &str) -> bool {
+        let domain = normalize_domain(domain);
+        let related = related_domains(&domain);
+        std::iter::once(&self.site_name)
+            .chain(&self.aliases)
+            .map(|d| normalize_domain(d))
+            .any(|d| {
+                is_same_or_subdomain(&domain, &d)
+                    || related.iter().any(|r| is_same_or_subdomain(&d, r))
+            })
+    }
+
+    /// Identifier that is safe to use as (part of) an HTML element ID and CSS selector
+    pub fn dom_id(&self) -> String {
+        let hex_id = self
+            .id
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        format!("site-{}", hex_id)
+    }
+
+    /// Whether the site is neither deleted nor in the trash
+    pub fn is_live(&self) -> bool {
+        !self.deleted && self.trashed.is_none()
+    }
+
+    /// Whether both sites generate the same password
+    pub fn generates_same_password(&self, other: &Site) -> bool {
+        self.site_name == other.site_name
+            && self.counter == other.counter
+            && self.password_type == other.password_type
+    }
+
+    /// Tombstone replacing this site after deletion (keeps no data apart from the ID)
+    pub fn tombstone(&self, modified: u64) -> Site {
+        Site {
+            id: self.id.clone(),
+            clock: self.clock + 1,
+            deleted: true,
+            modified,
+            ..Site::default()
+        }
+    }
+}
+
+/// Order of the site list when the filter contains no free text to rank by
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    #[default]
+    Name,
+    LastUsed,
+    MostUsed,
+    RecentlyModified,
+    OldestPassword,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 5] = [
+        SortOrder::Name,
+        SortOrder::LastUsed,
+        SortOrder::MostUsed,
+        SortOrder::RecentlyModified,
+        SortOrder::OldestPassword,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortOrder::Name => "Name",
+            SortOrder::LastUsed => "Last used",
+            SortOrder::MostUsed => "Most used",
+            SortOrder::RecentlyModified => "Recently modified",
+            SortOrder::OldestPassword => "Oldest password",
+        }
+    }
+
+    fn parse(label: &str) -> SortOrder {
+        SortOrder::ALL
+            .into_iter()
+            .find(|o| o.label() == label)
+            .unwrap_or_default()
+    }
+
+    /// Sort sites that are already sorted by name (the sort is stable, so ties stay alphabetical)
+    pub fn sort(&self, sites: &mut [Site]) {
+        match self {
+            SortOrder::Name => {}
+            SortOrder::LastUsed => sites.sort_by(|s1, s2| s2.last_used.cmp(&s1.last_used)),
+            SortOrder::MostUsed => sites.sort_by(|s1, s2| s2.use_count.cmp(&s1.use_count)),
+            SortOrder::RecentlyModified => sites.sort_by(|s1, s2| s2.modified.cmp(&s1.modified)),
+            // passwords of unknown age come first
+            SortOrder::OldestPassword => sites.sort_by(|s1, s2| s1.rotated.cmp(&s2.rotated)),
+        }
+    }
+}
+
+/// Copy text to the clipboard
+///
+/// The asynchronous clipboard API is only available via web-sys' unstable APIs, so it is called
+/// dynamically.
+fn copy_to_clipboard(text: &str) -> Result<(), JsValue> {
+    let navigator = js_sys::Reflect::get(&window(), &"navigator".into())?;
+    let clipboard = js_sys::Reflect::get(&navigator, &"clipboard".into())?;
+    let write_text = js_sys::Reflect::get(&clipboard, &"writeText".into())?;
+    js_sys::Function::from(write_text).call1(&clipboard, &text.into())?;
+    Ok(())
 }
 
 #[component]
@@ -18,57 +227,303 @@ pub fn Sites(cx: Scope) -> impl IntoView {
 
     let store = use_context::<RwStorage>(cx).unwrap().0;
 
-    let filter = create_rw_signal(cx, "".to_string());
-    let sites = move || {
+    let filter = use_context::<RwSiteFilter>(cx).unwrap().0;
+    let settings = move || {
         store()
+            .decrypt_settings(&login_name(), &storage_password())
+            .unwrap_or_default()
+    };
+    let saved_searches = move || settings().saved_searches;
+    let sort_order = move || settings().sort_order;
+    let favorites_only = move || settings().favorites_only;
+    let rotation = move || settings().rotation;
+    let overdue_only = create_rw_signal(cx, false);
+    let toggle_favorites_only = move |_| {
+        let mut settings = settings();
+        settings.favorites_only = !settings.favorites_only;
+        store.update(|data| data.store_settings(&login_name(), &storage_password(), &settings));
+    };
+    let set_sort_order = move |order: SortOrder| {
+        let mut settings = settings();
+        settings.sort_order = order;
+        store.update(|data| data.store_settings(&login_name(), &storage_password(), &settings));
+    };
+    let update_saved_searches = move |f: &dyn Fn(&mut Vec<SavedSearch>)| {
+        let mut settings = settings();
+        f(&mut settings.saved_searches);
+        store.update(|data| data.store_settings(&login_name(), &storage_password(), &settings));
+    };
+    // the dropdown of saved searches is toggled here, bootstrap.min.js has no dropdowns
+    let saved_searches_open = create_rw_signal(cx, false);
+
+    let save_search = move |_| {
+        let query = filter().trim().to_string();
+        let name = window()
+            .prompt_with_message_and_default("Name of the saved search", &query)
+            .ok()
+            .flatten()
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty());
+        if let Some(name) = name {
+            update_saved_searches(&|searches| {
+                searches.retain(|s| s.name != name);
+                searches.push(SavedSearch {
+                    name: name.clone(),
+                    query: query.clone(),
+                });
+            });
+        }
+    };
+
+    let new_site_url = create_rw_signal(cx, "".to_string());
+    // a pasted URL that matches no site can be used to create one
+    let filter_domain = move || domain_from_url(&filter());
+    let parsed_filter = move || Query::parse(&filter());
+    let sites = create_memo(cx, move |_| {
+        let sites = store()
             .decrypt_sites(&login_name(), &storage_password())
-            .unwrap_or(Vec::new())
+            .unwrap_or(Vec::new());
+        // fall back to plain text search while the query has syntax errors
+        let query = parsed_filter().unwrap_or_else(|_| Query::text(&filter()));
+        let mut sites = search(sites, &query, timestamp());
+        if overdue_only() {
+            let rotation = rotation();
+            sites.retain(|s| rotation.overdue_days(s, timestamp()).is_some());
+        }
+        if query.text_terms().is_empty() {
+            sort_order().sort(&mut sites);
+        }
+        sites
+    });
+    let favorite_sites = move || {
+        sites()
             .into_iter()
-            .filter(|s| {
-                if filter().chars().all(|c| c.is_lowercase()) {
-                    // filter string contains a capital letter => filter case-sensitive
-                    s.site_name.to_lowercase().contains(&filter())
-                } else {
-                    // filter string is all lowercase => filter case-insensitive
-                    s.site_name.contains(&filter())
+            .filter(|s| s.favorite)
+            .collect::<Vec<_>>()
+    };
+    let other_sites = move || {
+        sites()
+            .into_iter()
+            .filter(|s| !s.favorite && !favorites_only())
+            .collect::<Vec<_>>()
+    };
+    // sites ranked by a text search are listed without groups
+    let ranked = move || {
+        parsed_filter()
+            .map(|q| !q.text_terms().is_empty())
+            .unwrap_or(!filter().trim().is_empty())
+    };
+    let collapsed = create_rw_signal::<Vec<String>>(cx, Vec::new());
+    let toggle_collapsed = move |path: String| {
+        collapsed.update(|c| {
+            if c.contains(&path) {
+                c.retain(|p| *p != path);
+            } else {
+                c.push(path);
+            }
+        })
+    };
+    let groups = move || all_groups(&sites());
+    let selecting = create_rw_signal(cx, false);
+    let selection = create_rw_signal::<Vec<String>>(cx, Vec::new());
+    let toggle_selecting = move |_| {
+        selection.set(Vec::new());
+        selecting.set(!selecting());
+    };
+    let visible_ids = Signal::derive(cx, move || {
+        sites()
+            .into_iter()
+            .filter(|s| s.favorite || !favorites_only())
+            .map(|s| s.id)
+            .collect::<Vec<_>>()
+    });
+    let site_cards = move |sites: Vec<Site>| {
+        let selection = selecting().then_some(selection);
+        let rotation = rotation();
+        sites
+            .into_iter()
+            .map(|site| {
+                let overdue = rotation.overdue_days(&site, timestamp());
+                view! {cx,
+                    <div class="col-lg-6">
+                        <SitePassword
+                            site=Signal::derive(cx, move || Some(site.clone()))
+                            query=Signal::derive(cx, move || filter())
+                            selection=selection
+                            overdue=overdue
+                        />
+                    </div>
                 }
             })
             .collect::<Vec<_>>()
     };
 
     view! { cx,
-        <div class="my-5 text-center">
-            <h1 class="display-4 text-light">{login_data().unwrap().name}"'s Password Store"</h1>
-        </div>
-
-        <SitePassword site=Signal::derive(cx, move || None)/>
+        <SitePassword site=Signal::derive(cx, move || None) prefill=new_site_url/>
 
         <hr />
         <div class="row justify-content-end mb-1">
-            <div class="col-12 col-md-6 col-lg-3">
+            <div class="col-12 col-md-4 col-lg-3 mb-1">
+                <div class="input-group">
+                    <span class="input-group-text">
+                        <i class="fa-solid fa-arrow-down-wide-short"/>
+                    </span>
+                    <select class="form-select" title="Sort order (when not searching for text)"
+                        on:change=move |ev| set_sort_order(SortOrder::parse(&event_target_value(&ev)))
+                    >
+                        {SortOrder::ALL.into_iter().map(|order| view! { cx,
+                            <option value=order.label() selected=move || sort_order() == order>{order.label()}</option>
+                        })
+                        .collect::<Vec<_>>()}
+                    </select>
+                </div>
+            </div>
+            <div class="col-12 col-md-8 col-lg-5">
                 <div class="input-group">
                     <span class="input-group-text">
                         <i class="fa-solid fa-filter"/>
                     </span>
-                    <input type="text" class="form-control" placeholder="Filter..."
+                    <button type="button" title="Select sites for bulk actions"
+                        class=move || if selecting() {"btn btn-secondary text-bg-override"} else {"btn btn-light btn-outline-secondary"}
+                        on:click=toggle_selecting
+                    >
+                        <i class="fa-solid fa-list-check" />
+                    </button>
+                    <button type="button" title="Show only sites overdue for rotation"
+                        class=move || if overdue_only() {"btn btn-danger"} else {"btn btn-light btn-outline-secondary"}
+                        on:click=move |_| overdue_only.set(!overdue_only())
+                    >
+                        <i class="fa-solid fa-hourglass-end" />
+                    </button>
+                    <button type="button" title="Show favorites only"
+                        class=move || if favorites_only() {"btn btn-warning"} else {"btn btn-light btn-outline-secondary"}
+                        on:click=toggle_favorites_only
+                    >
+                        <i class="fa-solid fa-star" />
+                    </button>
+                    <input type="text" class="form-control" placeholder="Filter, e.g. tag:work -type:PIN"
                         on:input=move |ev| {
                             filter.set(event_target_value(&ev));
                         }
                         prop:value=filter
                     />
+                    <button class="btn btn-light btn-outline-secondary" type="button" title="Save search"
+                        disabled=move || filter().trim().is_empty() || parsed_filter().is_err()
+                        on:click=save_search
+                    >
+                        <i class="fa-solid fa-bookmark" />
+                    </button>
+                    <button class="btn btn-light btn-outline-secondary dropdown-toggle" type="button"
+                        title="Saved searches"
+                        on:click=move |_| saved_searches_open.update(|open| *open = !*open)
+                    >
+                        <i class="fa-solid fa-list" />
+                    </button>
+                    <ul class="dropdown-menu dropdown-menu-end" class:show=saved_searches_open
+                        style="top: 100%; right: 0"
+                    >
+                        {move || saved_searches().is_empty().then(|| view! { cx,
+                            <li><span class="dropdown-item-text text-muted">"No saved searches"</span></li>
+                        })}
+                        {move || saved_searches().into_iter().map(|saved| {
+                            let query = saved.query.clone();
+                            let name = saved.name.clone();
+                            view! { cx,
+                                <li class="d-flex">
+                                    <a class="dropdown-item" href="#" title=saved.query.clone()
+                                        on:click=move |ev| {
+                                            ev.prevent_default();
+                                            filter.set(query.clone());
+                                            saved_searches_open.set(false);
+                                        }
+                                    >
+                                        {saved.name.clone()}
+                                    </a>
+                                    <button class="btn btn-sm btn-link text-danger" type="button" title="Remove"
+                                        on:click=move |_| {
+                                            update_saved_searches(&|searches| searches.retain(|s| s.name != name));
+                                        }
+                                    >
+                                        <i class="fa-solid fa-xmark" />
+                                    </button>
+                                </li>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                    </ul>
                 </div>
+                {move || parsed_filter().err().map(|e| view! { cx,
+                    <small class="text-danger">{e.to_string()}</small>
+                })}
             </div>
         </div>
 
+        {move || filter_domain().filter(|_| sites().is_empty()).map(|domain| view! { cx,
+            <div class="mb-3">
+                <button class="btn btn-light btn-outline-secondary" type="button"
+                    on:click=move |_| {
+                        new_site_url.set(filter());
+                        filter.set("".to_string());
+                    }
+                >
+                    <i class="fa-solid fa-plus" />{format!(" Create site for {}", domain)}
+                </button>
+            </div>
+        })}
+
+        <Show when=selecting fallback=|_| ()>
+            <BulkActions selection=selection visible=visible_ids />
+        </Show>
+
+        {move || (!favorite_sites().is_empty()).then(|| view! { cx,
+            <h5 class="text-muted"><i class="fa-solid fa-star" />" Favorites"</h5>
+            <div class="row">{site_cards(favorite_sites())}</div>
+        })}
+
+        <datalist id="site-groups">
+            {move || groups().into_iter().map(|g| view! { cx, <option value=g /> }).collect::<Vec<_>>()}
+        </datalist>
+
         <div class="row">
-            {move || sites().into_iter().map(|site| {
-                view! {cx,
-                    <div class="col-lg-6">
-                        <SitePassword site=Signal::derive(cx, move || Some(site.clone())) />
-                    </div>
-                }
-            })
-            .collect::<Vec<_>>()}
+            {move || if ranked() {
+                site_cards(other_sites()).into_view(cx)
+            } else {
+                group_sections(other_sites()).into_iter().map(|section| {
+                    let path = section.path.clone();
+                    let filter_path = section.path.clone();
+                    let is_collapsed = collapsed().contains(&section.path);
+                    let hidden = collapsed().iter().any(|c| *c != section.path && is_in_group(&section.path, c));
+                    if hidden {
+                        return ().into_view(cx);
+                    }
+                    if section.path.is_empty() {
+                        return site_cards(section.sites).into_view(cx);
+                    }
+                    view! { cx,
+                        <div class="col-12">
+                            <h5 class="text-muted mt-2" style=format!("padding-left: {}rem", 1.5 * section.depth as f32)>
+                                <button class="btn btn-sm btn-link text-muted p-0 me-1" type="button"
+                                    on:click=move |_| toggle_collapsed(path.clone())
+                                >
+                                    <i class=if is_collapsed {"fa-solid fa-chevron-right"} else {"fa-solid fa-chevron-down"} />
+                                </button>
+                                <i class="fa-solid fa-folder me-1" />
+                                {section.name}
+                                <span class="badge text-bg-light border ms-2">{section.total}</span>
+                                <button class="btn btn-sm btn-link text-muted ms-1" type="button" title="Show only this group"
+                                    on:click=move |_| filter.set(format!("group:\"{}\"", filter_path))
+                                >
+                                    <i class="fa-solid fa-filter" />
+                                </button>
+                            </h5>
+                        </div>
+                        {(!is_collapsed).then(|| site_cards(section.sites))}
+                    }
+                    .into_view(cx)
+                })
+                .collect::<Vec<_>>()
+                .into_view(cx)
+            }}
             // For some reason, the <For /> construct looses the sorting after filtering
             // we therefore use the (less efficient) version above
 
@@ -82,11 +537,26 @@ pub fn Sites(cx: Scope) -> impl IntoView {
             //     }
             // />
         </div>
+
+        <hr />
+        <RotationPolicies />
+        <Backup />
     }
 }
 
 #[component]
-fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
+fn SitePassword(
+    cx: Scope,
+    site: Signal<Option<Site>>,
+    // pasted URL to fill into the new-site card
+    #[prop(optional)] prefill: Option<RwSignal<String>>,
+    // search query whose matches are highlighted
+    #[prop(optional)] query: Option<Signal<String>>,
+    // IDs of the selected sites while in selection mode
+    #[prop(optional_no_strip)] selection: Option<RwSignal<Vec<String>>>,
+    // days by which the password rotation is overdue
+    #[prop(optional_no_strip)] overdue: Option<u64>,
+) -> impl IntoView {
     // from global context
     let login_data = use_context::<RwLoginData>(cx).unwrap().0;
     let login_name = move || login_data().unwrap().name;
@@ -94,6 +564,7 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
     let storage_password = move || login_data().unwrap().storage_password;
 
     let store = use_context::<RwStorage>(cx).unwrap().0;
+    let breach_filter = use_context::<RwBreachFilter>(cx).unwrap().0;
 
     // signals
     let site_name = create_rw_signal(cx, site().map(|s| s.site_name).unwrap_or("".to_string()));
@@ -104,50 +575,203 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
             .map(|s| s.password_type)
             .unwrap_or("Maximum".to_string()),
     );
+    let label = create_rw_signal(cx, site().map(|s| s.label).unwrap_or_default());
+    let aliases = create_rw_signal(cx, site().map(|s| s.aliases).unwrap_or_default());
+    let tags = create_rw_signal(cx, site().map(|s| s.tags).unwrap_or_default());
+    let favorite = create_rw_signal(cx, site().map(|s| s.favorite).unwrap_or_default());
+    let group = create_rw_signal(cx, site().map(|s| s.group).unwrap_or_default());
+    let rotation_days = create_rw_signal(cx, site().and_then(|s| s.rotation_days));
+    let rules = create_rw_signal(cx, site().map(|s| s.rules).unwrap_or_default());
+    let site_login = create_rw_signal(cx, site().map(|s| s.login_name).unwrap_or_default());
+    let url = create_rw_signal(cx, site().map(|s| s.url).unwrap_or_default());
+    let notes = create_rw_signal(cx, site().map(|s| s.notes).unwrap_or_default());
     let hide_pw = create_rw_signal(cx, site().is_some());
+    let new_name = create_rw_signal(cx, "".to_string());
+    let rename_error = create_rw_signal::<Option<String>>(cx, None);
+
+    if let Some(prefill) = prefill {
+        create_effect(cx, move |_| {
+            let input = prefill();
+            if let Some(domain) = domain_from_url(&input) {
+                site_name.set(domain);
+                if input.contains("://") {
+                    url.set(input.trim().to_string());
+                }
+                prefill.set("".to_string());
+            }
+        });
+    }
+
+    // apply the known password rules (and a complying type) when naming a new site
+    if site().is_none() {
+        // the effect's value: the applied rules with the type and counter suggested for them
+        type Applied = Option<(&'static str, Option<(&'static str, i32)>)>;
+        create_effect(cx, move |applied: Option<Applied>| {
+            let applied = applied.flatten();
+            let known = known_rules(&site_name()).map(|k| k.rules);
+            // rules entered by hand are left alone
+            let current = rules.with_untracked(|r| r.clone());
+            if !current.is_empty() && Some(current.as_str()) != applied.map(|(rules, _)| rules) {
+                return None;
+            }
+            match (known, applied) {
+                (Some(known), _) => {
+                    rules.set(known.to_string());
+                    let suggestion = PasswordRules::parse(known)
+                        .ok()
+                        .and_then(|r| r.suggest(&masterkey(), &site_name(), 1));
+                    if let Some((password_type, suggested_counter)) = suggestion {
+                        pw_type.set(password_type.to_string());
+                        counter.set(suggested_counter);
+                    }
+                    Some((known, suggestion))
+                }
+                // the name no longer matches: drop the rules together with the suggested type,
+                // unless the type or counter were changed by hand since
+                (None, Some((_, suggestion))) => {
+                    rules.set("".to_string());
+                    let unchanged =
+                        suggestion.map_or(false, |(password_type, suggested_counter)| {
+                            pw_type.with_untracked(|t| t == password_type)
+                                && counter.with_untracked(|c| *c == suggested_counter)
+                        });
+                    if unchanged {
+                        pw_type.set("Maximum".to_string());
+                        counter.set(1);
+                    }
+                    None
+                }
+                (None, None) => None,
+            }
+        });
+    }
 
     // derived signals
+    let settings_id = site()
+        .map(|s| format!("{}-settings", s.dom_id()))
+        .unwrap_or("new-site-settings".to_string());
 
-    let title = || {
+    let query = move || query.map(|q| q()).unwrap_or_default();
+    let title = move || {
         site()
-            .map(|_| site_name())
-            .unwrap_or("New Site".to_string())
+            .map(|_| {
+                let name = if label().is_empty() {
+                    site_name()
+                } else {
+                    label()
+                };
+                highlighted(cx, &name, &query(), Matching::Fuzzy)
+            })
+            .unwrap_or("New Site".into_view(cx))
+    };
+    // login, URL and the matching line of the notes, shown while they match the search
+    let matched_details = move || {
+        let query = query();
+        let note = notes()
+            .lines()
+            .find(|line| has_highlights(line, &query, Matching::Substring))
+            .unwrap_or_default()
+            .to_string();
+        [
+            ("fa-user", site_login()),
+            ("fa-link", url()),
+            ("fa-note-sticky", note),
+        ]
+        .into_iter()
+        .filter(|(_, text)| site().is_some() && has_highlights(text, &query, Matching::Substring))
+        .map(|(icon, text)| {
+            view! { cx,
+                <small class="text-muted ms-2 fs-6">
+                    <i class=format!("fa-solid {} me-1", icon) />
+                    {highlighted(cx, &text, &query, Matching::Substring)}
+                </small>
+            }
+        })
+        .collect::<Vec<_>>()
     };
     let is_selected = move |selection| (pw_type() == selection).then(|| "selected");
+    let migration = move || site().and_then(|s| s.migration);
+
+    let edited_site = move || Site {
+        site_name: site_name(),
+        counter: counter(),
+        password_type: pw_type(),
+        label: label(),
+        aliases: aliases(),
+        tags: tags(),
+        favorite: favorite(),
+        group: group(),
+        rotation_days: rotation_days(),
+        rules: rules(),
+        login_name: site_login(),
+        url: url(),
+        notes: notes(),
+        ..site().unwrap_or_default()
+    };
 
     let add_site = move || {
         if site_name().len() > 0 {
             store.update(|data| {
-                data.add_site(
-                    &login_name(),
-                    &storage_password(),
-                    &site_name(),
-                    counter(),
-                    &pw_type(),
-                )
-                .unwrap_or_else(|e| log!("Error adding site: {}", e));
+                data.add_site(&login_name(), &storage_password(), edited_site())
+                    .unwrap_or_else(|e| log!("Error adding site: {}", e));
             });
         }
     };
 
     let update_site = move || {
         store.update(|data| {
-            data.update_site(
-                &login_name(),
-                &storage_password(),
-                &site_name(),
-                counter(),
-                &pw_type(),
-            )
-            .unwrap_or_else(|e| log!("Error updating site: {}", e));
+            data.update_site(&login_name(), &storage_password(), edited_site())
+                .unwrap_or_else(|e| log!("Error updating site: {}", e));
         });
     };
 
     let delete_site = move |_ev| {
-        store.update(|data| {
-            data.delete_site(&login_name(), &storage_password(), &site_name())
-                .unwrap_or_else(|e| log!("Error deleting site: {}", e))
-        });
+        if let Some(site) = site() {
+            let message = format!("Moved {} to the trash", site.display_name());
+            trash_with_undo(cx, vec![site.id], message);
+        }
+    };
+
+    let record_use = move || {
+        if let Some(site) = site() {
+            // untracked, so that the site cards are not re-rendered (which would hide the
+            // password again); the new order shows with the next change
+            store.update_untracked(|data| {
+                data.record_use(&login_name(), &storage_password(), &site.id)
+                    .unwrap_or_else(|e| log!("Error recording site usage: {}", e));
+                data.to_local_storage();
+            });
+        }
+    };
+    let reveal_on_click = move |_| {
+        if hide_pw() {
+            record_use();
+        }
+        hide_pw.set(!hide_pw());
+    };
+
+    let generate_password = move |name: &str| {
+        masterkey().generate_password(name, &pw_type().as_str().into(), counter())
+    };
+    let hidden = move |password: String| {
+        if hide_pw() {
+            "•".repeat(password.chars().count())
+        } else {
+            password
+        }
+    };
+
+    let rename_site = move |_ev| {
+        let renamed = Site {
+            site_name: new_name().trim().to_string(),
+            ..edited_site()
+        };
+        store.update(
+            |data| match data.update_site(&login_name(), &storage_password(), renamed) {
+                Ok(()) => new_name.set("".to_string()),
+                Err(e) => rename_error.set(Some(e.to_string())),
+            },
+        );
     };
 
     let save_site = move || {
@@ -168,6 +792,29 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
         }
     };
 
+    // violations of the site's password rules, `None` without rules
+    let compliance = move || {
+        (!rules().is_empty() && !site_name().is_empty()).then(|| {
+            PasswordRules::parse(&rules())
+                .map(|r| r.violations(&password()))
+                .map_err(|e| e.to_string())
+        })
+    };
+    let suggestion = move || {
+        PasswordRules::parse(&rules())
+            .ok()
+            .and_then(|r| r.suggest(&masterkey(), &site_name(), counter()))
+    };
+
+    let breached = move || {
+        site().is_some() && breach_filter().map_or(false, |filter| filter.contains(&password()))
+    };
+
+    let copy_on_click = move |_| {
+        copy_to_clipboard(&password()).unwrap_or_else(|e| log!("Error copying password: {:?}", e));
+        record_use();
+    };
+
     let save_on_click = move |ev: ev::MouseEvent| {
         ev.prevent_default();
 
@@ -176,6 +823,16 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
         site_name.set("".to_string());
         counter.set(1);
         pw_type.set("Maximum".to_string());
+        label.set("".to_string());
+        aliases.set(Vec::new());
+        tags.set(Vec::new());
+        favorite.set(false);
+        group.set("".to_string());
+        rotation_days.set(None);
+        rules.set("".to_string());
+        site_login.set("".to_string());
+        url.set("".to_string());
+        notes.set("".to_string());
     };
 
     view! { cx,
@@ -183,10 +840,57 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
             <div class="card mb-3 border-dark">
                 <div class=move || if site().is_none() {"card-header text-bg-secondary text-bg-override"} else {"card-header"}>
                     <div class="row">
-                        <span class="col-8 fs-4">{title()}</span>
+                        <span class="col-8 fs-4">
+                            {move || selection.zip(site()).map(|(selection, site)| {
+                                let id = site.id.clone();
+                                view! { cx,
+                                    <input class="form-check-input me-2" type="checkbox"
+                                        prop:checked=move || selection().contains(&site.id)
+                                        on:change=move |ev| {
+                                            let checked = event_target::<web_sys::HtmlInputElement>(&ev).checked();
+                                            selection.update(|s| {
+                                                s.retain(|i| *i != id);
+                                                if checked {
+                                                    s.push(id.clone());
+                                                }
+                                            });
+                                        }
+                                    />
+                                }
+                            })}
+                            {title}
+                            {move || (site().is_some() && !label().is_empty()).then(|| view! { cx,
+                                // the site name the password is generated for
+                                <small class="text-muted ms-2 fs-6">{highlighted(cx, &site_name(), &query(), Matching::Fuzzy)}</small>
+                            })}
+                            {move || tags().into_iter().map(|tag| view! { cx,
+                                <span class="badge text-bg-light border ms-2 fs-6">{highlighted(cx, &tag, &query(), Matching::Fuzzy)}</span>
+                            })
+                            .collect::<Vec<_>>()}
+                            {matched_details}
+                            {move || match migration() {
+                                Some(MigrationStatus::Pending) => view! { cx,
+                                    <span class="badge text-bg-warning ms-2 fs-6">"needs migration"</span>
+                                }.into_any(),
+                                Some(MigrationStatus::Changed) => view! { cx,
+                                    <span class="badge text-bg-info ms-2 fs-6">"needs verification"</span>
+                                }.into_any(),
+                                _ => view! { cx, <span /> }.into_any(),
+                            }}
+                            {move || breached().then(|| view! { cx,
+                                <span class="badge text-bg-danger ms-2 fs-6" title="The password appears in the breach filter, increment the counter">
+                                    <i class="fa-solid fa-shield-virus" />" breached"
+                                </span>
+                            })}
+                            {overdue.map(|days| view! { cx,
+                                <span class="badge text-bg-danger ms-2 fs-6" title=format!("Rotation overdue by {} days", days)>
+                                    <i class="fa-solid fa-hourglass-end" />" rotate"
+                                </span>
+                            })}
+                        </span>
                         <div class="col-4">
                             // Collapse edit fields button
-                            <button class="btn btn-light btn-outline-secondary float-end" type="button" data-bs-toggle="collapse" data-bs-target=format!("#{}Collapse", site_name())>
+                            <button class="btn btn-light btn-outline-secondary float-end" type="button" data-bs-toggle="collapse" data-bs-target=format!("#{}", settings_id)>
                                 <i class="fa-solid fa-ellipsis" />
                             </button>
 
@@ -206,6 +910,20 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
                                 }
                             }}
 
+                            // Favorite toggle
+                            <button class="btn btn-light btn-outline-warning float-end" type="button"
+                                title=move || if favorite() {"Remove from favorites"} else {"Add to favorites"}
+                                on:click=move |_| {
+                                    favorite.set(!favorite());
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                            >
+                                // only the solid icon style is loaded, so the color shows whether the site is a favorite
+                                <i class=move || if favorite() {"fa-solid fa-star text-warning"} else {"fa-solid fa-star text-muted"} />
+                            </button>
+
                         </div>
                     </div>
                 </div>
@@ -225,6 +943,12 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
                                     prop:value=site_name
                                 />
                             </div>
+                            {move || known_rules(&site_name()).filter(|k| rules() == k.rules).map(|known| view! { cx,
+                                <div class="form-text mb-2">
+                                    <i class="fa-solid fa-circle-info" />
+                                    {format!(" Applied the known password rules of {}", known.domain)}
+                                </div>
+                            })}
                         }.into_any()
                     } else {view! { cx, <div />}.into_any()}}
 
@@ -241,14 +965,23 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
                             // toggle password hiding
                             class="btn btn-light btn-outline-secondary"
                             type="button"
-                            on:click=move |_| hide_pw.set(!hide_pw())
+                            on:click=reveal_on_click
                         >
                             <i class=move || if hide_pw() {"fa-solid fa-eye"} else {"fa-solid fa-eye-slash"} />
                         </button>
+                        <button
+                            // copy password to the clipboard
+                            class="btn btn-light btn-outline-secondary"
+                            type="button"
+                            title="Copy password"
+                            on:click=copy_on_click
+                        >
+                            <i class="fa-solid fa-copy" />
+                        </button>
                     </div>
 
                     // Password Settings
-                    <div class="row collapse" id=format!("{}Collapse", site_name())>
+                    <div class="row collapse" id=settings_id.clone()>
                         <div class="col-6">
 
                             // Password Type Select
@@ -290,9 +1023,252 @@ fn SitePassword(cx: Scope, site: Signal<Option<Site>>) -> impl IntoView {
                                 prop:value=counter
                             />
                         </div>
+
+                        // Strength of the password type
+                        <div class="col-12 mt-1">
+                            <TemplateStrengthInfo password_type=pw_type />
+                        </div>
+
+                        // Password requirements of the site
+                        <div class="col-12 mt-2">
+                            <label>"Password Rules"</label>
+                            <input class="form-control font-monospace" type="text"
+                                placeholder="e.g. minlength: 8; maxlength: 16; required: lower, upper; allowed: digit;"
+                                on:change=move |ev| {
+                                    rules.set(event_target_value(&ev).trim().to_string());
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=rules
+                            />
+                            {move || compliance().map(|result| match result {
+                                Err(e) => view! { cx, <small class="text-danger">{e}</small> }.into_view(cx),
+                                Ok(violations) if violations.is_empty() => view! { cx,
+                                    <small class="text-success"><i class="fa-solid fa-check" />" Password complies with the rules"</small>
+                                }.into_view(cx),
+                                Ok(violations) => view! { cx,
+                                    <small class="text-danger">
+                                        <i class="fa-solid fa-triangle-exclamation" />
+                                        {format!(" Password {}", violations.join(", "))}
+                                    </small>
+                                    {match suggestion() {
+                                        Some((password_type, suggested_counter)) => view! { cx,
+                                            <div>
+                                                <small>{format!("Suggestion: {}, counter {} ", password_type, suggested_counter)}</small>
+                                                <button class="btn btn-sm btn-light btn-outline-secondary" type="button"
+                                                    on:click=move |_| {
+                                                        pw_type.set(password_type.to_string());
+                                                        counter.set(suggested_counter);
+                                                        if site().is_some() {
+                                                            save_site();
+                                                        }
+                                                    }
+                                                >
+                                                    "Apply"
+                                                </button>
+                                            </div>
+                                        }.into_view(cx),
+                                        None => view! { cx,
+                                            <div><small>"No password type complies with these rules."</small></div>
+                                        }.into_view(cx),
+                                    }}
+                                }.into_view(cx),
+                            })}
+                        </div>
+
+                        // Display label
+                        <div class="col-12 mt-2">
+                            <label>"Label"</label>
+                            <input class="form-control" type="text" placeholder="Shown instead of the site name"
+                                on:change=move |ev| {
+                                    label.set(event_target_value(&ev).trim().to_string());
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=label
+                            />
+                        </div>
+
+                        // Alias domains
+                        <div class="col-12 mt-2">
+                            <label>"Aliases"</label>
+                            <input class="form-control" type="text" placeholder="Other domains using this account, comma separated"
+                                on:change=move |ev| {
+                                    aliases.set(
+                                        event_target_value(&ev)
+                                            .split(',')
+                                            .map(normalize_domain)
+                                            .filter(|a| !a.is_empty())
+                                            .collect()
+                                    );
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=move || aliases().join(", ")
+                            />
+                        </div>
+
+                        // Group
+                        <div class="col-12 mt-2">
+                            <label>"Group"</label>
+                            <input class="form-control" type="text" list="site-groups" placeholder="e.g. Work/Infrastructure"
+                                on:change=move |ev| {
+                                    group.set(normalize_group(&event_target_value(&ev)));
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=group
+                            />
+                        </div>
+
+                        // Rotation policy
+                        <div class="col-12 mt-2">
+                            <label>"Rotate every ... days"</label>
+                            <input class="form-control" type="number" min="0" placeholder="Policy of the tags or default"
+                                on:change=move |ev| {
+                                    rotation_days.set(event_target_value(&ev).trim().parse().ok());
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=move || rotation_days().map(|d| d.to_string()).unwrap_or_default()
+                            />
+                            {move || site().and_then(|s| password_age_days(&s, timestamp())).map(|age| view! { cx,
+                                <small class="text-muted">{format!("Password last changed {} days ago", age)}</small>
+                            })}
+                        </div>
+
+                        // Tags
+                        <div class="col-12 mt-2">
+                            <label>"Tags"</label>
+                            <input class="form-control" type="text" placeholder="Comma separated"
+                                on:change=move |ev| {
+                                    tags.set(
+                                        event_target_value(&ev)
+                                            .split(',')
+                                            .map(|t| t.trim().to_string())
+                                            .filter(|t| !t.is_empty())
+                                            .collect()
+                                    );
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=move || tags().join(", ")
+                            />
+                        </div>
+
+                        // Account details
+                        <div class="col-6 mt-2">
+                            <label>"Login"</label>
+                            <input class="form-control" type="text"
+                                on:change=move |ev| {
+                                    site_login.set(event_target_value(&ev));
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=site_login
+                            />
+                        </div>
+                        <div class="col-6 mt-2">
+                            <label>"URL"</label>
+                            <input class="form-control" type="text"
+                                on:change=move |ev| {
+                                    url.set(event_target_value(&ev));
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=url
+                            />
+                        </div>
+                        <div class="col-12 mt-2">
+                            <label>"Notes"</label>
+                            <textarea class="form-control" rows="2"
+                                on:change=move |ev| {
+                                    notes.set(event_target_value(&ev));
+                                    if site().is_some() {
+                                        save_site();
+                                    }
+                                }
+                                prop:value=notes
+                            />
+                        </div>
+
+                        {move || site().filter(|s| !s.history.is_empty()).map(|s| view! { cx,
+                            // Previous configurations
+                            <div class="col-12 mt-2">
+                                <label>"History"</label>
+                                <PasswordHistory history=s.history />
+                            </div>
+                        })}
+
+                        {move || site().is_some().then(|| view! { cx,
+                            // Rename site
+                            <div class="col-12 mt-2">
+                                <label>"Rename"</label>
+                                <div class="input-group">
+                                    <input class="form-control" type="text" placeholder="New Site Name"
+                                        on:input=move |ev| {
+                                            rename_error.set(None);
+                                            new_name.set(event_target_value(&ev));
+                                        }
+                                        prop:value=new_name
+                                    />
+                                    <button class="btn btn-light btn-outline-danger" type="button"
+                                        disabled=move || new_name().trim().is_empty() || new_name().trim() == site_name()
+                                        on:click=rename_site
+                                    >
+                                        "Rename"
+                                    </button>
+                                </div>
+                                <Show
+                                    when=move || !new_name().trim().is_empty() && new_name().trim() != site_name()
+                                    fallback=|_| ()
+                                >
+                                    <div class="alert alert-warning mt-2 mb-0">
+                                        <i class="fa-solid fa-triangle-exclamation" />
+                                        " Renaming changes the generated password. Update it on the site afterwards."
+                                        <div class="font-monospace mt-1">
+                                            "Old: "{move || hidden(generate_password(&site_name()))}
+                                            <br />
+                                            "New: "{move || hidden(generate_password(new_name().trim()))}
+                                        </div>
+                                    </div>
+                                </Show>
+                                {move || rename_error().map(|e| view! { cx,
+                                    <div class="text-danger mt-1">{e}</div>
+                                })}
+                            </div>
+                        })}
                     </div>
                 </div>
             </div>
         </form>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_domain_via_aliases_and_related_domains() {
+        let site = Site {
+            site_name: "accounts.google.com".to_string(),
+            aliases: vec!["example.org".to_string()],
+            ..Default::default()
+        };
+        assert!(site.matches_domain("mail.accounts.google.com"));
+        assert!(site.matches_domain("www.example.org"));
+        // a subdomain of a domain sharing accounts with the site name
+        assert!(site.matches_domain("youtube.com"));
+        assert!(!site.matches_domain("example.com"));
+        assert!(!site.matches_domain("gitlab.io"));
+    }
+}
@@ -2,30 +2,106 @@ use std::{collections::HashMap, error::Error};
 
 use magic_crypt::{new_magic_crypt, MagicCryptTrait};
 use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsValue;
 
-use crate::sites::Site;
+use crate::{
+    history::with_previous_configuration, merge::merge_vaults, settings::Settings, sites::Site,
+};
 
 const STORAGE_KEY_DB: &str = "db";
+const STORAGE_KEY_SETTINGS: &str = "settings";
 const STORAGE_KEY_LAST_USER: &str = "last_user";
+const STORAGE_KEY_MIN_PASSWORD_SCORE: &str = "min_password_score";
+
+/// Strength score (see `strength::estimate`) master passwords of new identities need by default
+pub const DEFAULT_MIN_PASSWORD_SCORE: u8 = 3;
+
+pub const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
 
 #[derive(Clone, Debug)]
 pub struct EncryptedStorage {
     db: HashMap<String, EncryptedSites>,
+    settings: HashMap<String, EncryptedSettings>,
     pub last_user: String,
+    /// minimum strength score of the master password of new identities
+    pub min_password_score: u8,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct EncryptedSites(String);
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct EncryptedSettings(String);
+
 // TODO: proper error types (thiserror?)
 
+/// Current time in milliseconds since the epoch
+#[cfg(target_arch = "wasm32")]
+pub fn timestamp() -> u64 {
+    js_sys::Date::now() as u64
+}
+
+/// Current time in milliseconds since the epoch (native builds, e.g. unit tests)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Human readable local date and time of a timestamp (milliseconds since the epoch)
+#[cfg(target_arch = "wasm32")]
+pub fn format_timestamp(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "unknown".to_string();
+    }
+    let date = js_sys::Date::new(&JsValue::from_f64(timestamp as f64));
+    date.to_locale_string("default", &JsValue::UNDEFINED).into()
+}
+
+/// Date and time (UTC) of a timestamp (native builds, e.g. unit tests)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn format_timestamp(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "unknown".to_string();
+    }
+    let minutes = timestamp % MS_PER_DAY / 60_000;
+
+    // proleptic Gregorian date of the days since the epoch
+    let days = (timestamp / MS_PER_DAY) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let m = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * m + 2) / 5 + 1;
+    let month = if m < 10 { m + 3 } else { m - 9 };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
 impl EncryptedSites {
     pub fn decrypt(&self, password: &str) -> Result<Vec<Site>, Box<dyn Error>> {
         let mc = new_magic_crypt!(password, 256);
         let json_string = mc
             .decrypt_base64_to_string(&self.0)
             .map_err(|e| format!("Decryption error: {}", e))?;
-        Ok(serde_json::from_str(&json_string).unwrap_or(Vec::new()))
+        let mut sites: Vec<Site> = serde_json::from_str(&json_string).unwrap_or(Vec::new());
+        for site in sites.iter_mut().filter(|s| s.id.is_empty()) {
+            site.id = Site::legacy_id(&site.site_name);
+        }
+        Ok(sites)
     }
 
     pub fn from_sites(sites: &[Site], password: &str) -> EncryptedSites {
@@ -36,6 +112,23 @@ impl EncryptedSites {
     }
 }
 
+impl EncryptedSettings {
+    pub fn decrypt(&self, password: &str) -> Result<Settings, Box<dyn Error>> {
+        let mc = new_magic_crypt!(password, 256);
+        let json_string = mc
+            .decrypt_base64_to_string(&self.0)
+            .map_err(|e| format!("Decryption error: {}", e))?;
+        Ok(serde_json::from_str(&json_string).unwrap_or_default())
+    }
+
+    pub fn from_settings(settings: &Settings, password: &str) -> EncryptedSettings {
+        let mc = new_magic_crypt!(password, 256);
+        let json_string = serde_json::to_string(settings).unwrap();
+
+        EncryptedSettings(mc.encrypt_str_to_base64(&json_string))
+    }
+}
+
 impl EncryptedStorage {
     pub fn from_local_storage() -> Self {
         let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
@@ -46,19 +139,40 @@ impl EncryptedStorage {
             .flatten()
             .unwrap_or("[]".to_string());
 
+        let settings_str = local_storage
+            .get_item(STORAGE_KEY_SETTINGS)
+            .ok()
+            .flatten()
+            .unwrap_or("{}".to_string());
+
         let last_user = local_storage
             .get_item(STORAGE_KEY_LAST_USER)
             .ok()
             .flatten()
             .unwrap_or("".to_string());
 
+        let min_password_score = local_storage
+            .get_item(STORAGE_KEY_MIN_PASSWORD_SCORE)
+            .ok()
+            .flatten()
+            .and_then(|score| score.parse().ok())
+            .unwrap_or(DEFAULT_MIN_PASSWORD_SCORE);
+
         let db: HashMap<String, EncryptedSites> =
             serde_json::from_str(&db_str).unwrap_or_else(|_| {
                 local_storage.clear().unwrap();
                 HashMap::default()
             });
 
-        Self { db, last_user }
+        let settings: HashMap<String, EncryptedSettings> =
+            serde_json::from_str(&settings_str).unwrap_or_default();
+
+        Self {
+            db,
+            settings,
+            last_user,
+            min_password_score,
+        }
     }
 
     pub fn to_local_storage(&self) -> () {
@@ -66,16 +180,63 @@ impl EncryptedStorage {
 
         let json_data = serde_json::to_string(&self.db).unwrap();
         local_storage.set_item(STORAGE_KEY_DB, &json_data).unwrap();
+        let json_settings = serde_json::to_string(&self.settings).unwrap();
+        local_storage
+            .set_item(STORAGE_KEY_SETTINGS, &json_settings)
+            .unwrap();
         local_storage
             .set_item(STORAGE_KEY_LAST_USER, &self.last_user)
             .unwrap();
+        local_storage
+            .set_item(
+                STORAGE_KEY_MIN_PASSWORD_SCORE,
+                &self.min_password_score.to_string(),
+            )
+            .unwrap();
+    }
+
+    /// Whether any sites or settings are stored for the user
+    pub fn is_known_user(&self, name: &str) -> bool {
+        self.db.contains_key(name) || self.settings.contains_key(name)
+    }
+
+    pub fn decrypt_settings(&self, name: &str, password: &str) -> Result<Settings, Box<dyn Error>> {
+        if let Some(encrypted_settings) = self.settings.get(name) {
+            encrypted_settings.decrypt(password)
+        } else {
+            Ok(Settings::default())
+        }
+    }
+
+    pub fn store_settings(&mut self, name: &str, password: &str, settings: &Settings) {
+        self.settings.insert(
+            name.to_string(),
+            EncryptedSettings::from_settings(settings, password),
+        );
+    }
+
+    pub fn encrypted_sites(&self, name: &str) -> Option<EncryptedSites> {
+        self.db.get(name).cloned()
     }
 
     pub fn decrypt_sites(&self, name: &str, password: &str) -> Result<Vec<Site>, Box<dyn Error>> {
+        let mut sites = self
+            .decrypt_all_sites(name, password)?
+            .into_iter()
+            .filter(|s| s.is_live())
+            .collect::<Vec<_>>();
+        sites.sort_by(|s1, s2| s1.display_name().cmp(s2.display_name()));
+        Ok(sites)
+    }
+
+    /// All sites of a user including the tombstones of deleted sites (required for merging)
+    pub fn decrypt_all_sites(
+        &self,
+        name: &str,
+        password: &str,
+    ) -> Result<Vec<Site>, Box<dyn Error>> {
         if let Some(encrypted_sites) = self.db.get(name) {
-            let mut sites = encrypted_sites.decrypt(password)?;
-            sites.sort_by(|s1, s2| s1.site_name.cmp(&s2.site_name));
-            Ok(sites)
+            encrypted_sites.decrypt(password)
         } else {
             Ok(Vec::new())
         }
@@ -85,14 +246,15 @@ impl EncryptedStorage {
         &mut self,
         login_name: &str,
         storage_password: &str,
-        site_name: &str,
-        counter: i32,
-        pw_type: &str,
+        site: Site,
     ) -> Result<(), Box<dyn Error>> {
         let new_site = Site {
-            site_name: site_name.to_string(),
-            counter,
-            password_type: pw_type.to_string(),
+            id: Site::new_id(),
+            clock: 1,
+            deleted: false,
+            modified: timestamp(),
+            rotated: timestamp(),
+            ..site
         };
 
         let mut user_sites = if let Some(encrypted_sites) = self.db.get(login_name) {
@@ -101,7 +263,10 @@ impl EncryptedStorage {
             Vec::new()
         };
 
-        if !user_sites.iter().any(|s| s.site_name == site_name) {
+        if !user_sites
+            .iter()
+            .any(|s| s.is_live() && s.site_name == new_site.site_name)
+        {
             user_sites.push(new_site);
             self.db.insert(
                 login_name.to_string(),
@@ -116,16 +281,18 @@ impl EncryptedStorage {
         &mut self,
         login_name: &str,
         storage_password: &str,
-        site_name: &str,
-        counter: i32,
-        pw_type: &str,
+        site: Site,
     ) -> Result<(), Box<dyn Error>> {
-        let new_site = Site {
-            site_name: site_name.to_string(),
-            counter,
-            password_type: pw_type.to_string(),
-        };
+        self.update_sites(login_name, storage_password, vec![site])
+    }
 
+    /// Update several sites (matched by ID) at once
+    pub fn update_sites(
+        &mut self,
+        login_name: &str,
+        storage_password: &str,
+        sites: Vec<Site>,
+    ) -> Result<(), Box<dyn Error>> {
         let mut user_sites = if let Some(encrypted_sites) = self.db.get(login_name) {
             encrypted_sites.decrypt(storage_password)?
         } else {
@@ -133,26 +300,194 @@ impl EncryptedStorage {
             return Ok(());
         };
 
-        let res = user_sites
+        let mut changed = false;
+        for site in sites {
+            // site names must stay unique (e.g. when renaming)
+            if user_sites
+                .iter()
+                .any(|s| s.is_live() && s.id != site.id && s.site_name == site.site_name)
+            {
+                return Err(format!("A site named '{}' already exists", site.site_name).into());
+            }
+
+            if let Some(s) = user_sites
+                .iter_mut()
+                .find(|s| !s.deleted && s.id == site.id)
+            {
+                // keep the previous configuration to be able to regenerate its password
+                let (rotated, history) = if s.generates_same_password(&site) {
+                    (s.rotated, s.history.clone())
+                } else {
+                    (timestamp(), with_previous_configuration(s, timestamp()))
+                };
+                *s = Site {
+                    clock: s.clock + 1,
+                    deleted: false,
+                    modified: timestamp(),
+                    rotated,
+                    history,
+                    // usage is only tracked by `record_use`
+                    last_used: s.last_used,
+                    use_count: s.use_count,
+                    ..site
+                };
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return Ok(());
+        }
+        self.db.insert(
+            login_name.to_string(),
+            EncryptedSites::from_sites(&user_sites, storage_password),
+        );
+
+        Ok(())
+    }
+
+    /// Sites in the trash, most recently trashed first
+    pub fn decrypt_trashed_sites(
+        &self,
+        name: &str,
+        password: &str,
+    ) -> Result<Vec<Site>, Box<dyn Error>> {
+        let mut sites = self
+            .decrypt_all_sites(name, password)?
+            .into_iter()
+            .filter(|s| !s.deleted && s.trashed.is_some())
+            .collect::<Vec<_>>();
+        sites.sort_by(|s1, s2| s2.trashed.cmp(&s1.trashed));
+        Ok(sites)
+    }
+
+    /// Move sites (by ID) to the trash
+    pub fn trash_sites(
+        &mut self,
+        login_name: &str,
+        storage_password: &str,
+        site_ids: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut user_sites = self.decrypt_all_sites(login_name, storage_password)?;
+        for site in user_sites
             .iter_mut()
-            .find(|s| s.site_name == site_name)
-            .map(|s| *s = new_site.clone());
+            .filter(|s| s.is_live() && site_ids.contains(&s.id))
+        {
+            site.trashed = Some(timestamp());
+            site.clock += 1;
+            site.modified = timestamp();
+        }
+        self.store_sites(login_name, storage_password, &user_sites);
+        Ok(())
+    }
 
-        if res.is_some() {
-            self.db.insert(
-                login_name.to_string(),
-                EncryptedSites::from_sites(&user_sites, storage_password),
-            );
-        };
+    /// Restore sites (by ID) from the trash
+    pub fn restore_sites(
+        &mut self,
+        login_name: &str,
+        storage_password: &str,
+        site_ids: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut user_sites = self.decrypt_all_sites(login_name, storage_password)?;
+        for idx in 0..user_sites.len() {
+            let site = &user_sites[idx];
+            if site.deleted || site.trashed.is_none() || !site_ids.contains(&site.id) {
+                continue;
+            }
+            // a new site may have taken the name in the meantime
+            if user_sites
+                .iter()
+                .any(|s| s.is_live() && s.site_name == site.site_name)
+            {
+                return Err(format!("A site named '{}' already exists", site.site_name).into());
+            }
+            let site = &mut user_sites[idx];
+            site.trashed = None;
+            site.clock += 1;
+            site.modified = timestamp();
+        }
+        self.store_sites(login_name, storage_password, &user_sites);
+        Ok(())
+    }
+
+    /// Permanently delete the sites that have been in the trash for longer than configured
+    pub fn purge_expired_trash(
+        &mut self,
+        login_name: &str,
+        storage_password: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let retention_days = self
+            .decrypt_settings(login_name, storage_password)?
+            .trash
+            .retention_days;
+        if retention_days == 0 {
+            // keep trashed sites until purged manually
+            return Ok(());
+        }
+
+        let cutoff = timestamp().saturating_sub(retention_days as u64 * MS_PER_DAY);
+        let expired = self
+            .decrypt_trashed_sites(login_name, storage_password)?
+            .into_iter()
+            .filter(|s| s.trashed.map_or(false, |t| t < cutoff))
+            .map(|s| s.id)
+            .collect::<Vec<_>>();
+        if !expired.is_empty() {
+            self.delete_sites(login_name, storage_password, &expired)?;
+        }
+        Ok(())
+    }
+
+    /// Record that the password of a site was revealed or copied
+    ///
+    /// Usage is no change of the site, so neither its clock nor its modification time are touched;
+    /// synchronization merges the usage of both copies instead (see `merge_vaults`).
+    pub fn record_use(
+        &mut self,
+        login_name: &str,
+        storage_password: &str,
+        site_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut user_sites = self.decrypt_all_sites(login_name, storage_password)?;
+        if let Some(site) = user_sites
+            .iter_mut()
+            .find(|s| !s.deleted && s.id == site_id)
+        {
+            site.last_used = timestamp();
+            site.use_count += 1;
+            self.store_sites(login_name, storage_password, &user_sites);
+        }
+        Ok(())
+    }
 
+    /// Merge sites received by a synchronization into the stored sites
+    ///
+    /// The sites stored meanwhile are merged rather than overwritten, so changes made while a
+    /// synchronization request was in flight are kept (and uploaded by the next one).
+    pub fn merge_synced_sites(
+        &mut self,
+        login_name: &str,
+        storage_password: &str,
+        sites: &[Site],
+    ) -> Result<(), Box<dyn Error>> {
+        let current = self.decrypt_all_sites(login_name, storage_password)?;
+        self.store_sites(login_name, storage_password, &merge_vaults(&current, sites));
         Ok(())
     }
 
-    pub fn delete_site(
+    pub fn store_sites(&mut self, login_name: &str, storage_password: &str, sites: &[Site]) {
+        self.db.insert(
+            login_name.to_string(),
+            EncryptedSites::from_sites(sites, storage_password),
+        );
+    }
+
+    /// Delete several sites (by ID) at once
+    pub fn delete_sites(
         &mut self,
         login_name: &str,
         storage_password: &str,
-        site_name: &str,
+        site_ids: &[String],
     ) -> Result<(), Box<dyn Error>> {
         let user_sites = if let Some(encrypted_sites) = self.db.get(login_name) {
             encrypted_sites.decrypt(storage_password)?
@@ -160,9 +495,16 @@ impl EncryptedStorage {
             // no sites stored => no deletion possible
             return Ok(());
         };
+        // keep a tombstone so that merging with other copies does not resurrect the site
         let user_sites = user_sites
             .into_iter()
-            .filter(|s| s.site_name != site_name)
+            .map(|s| {
+                if !s.deleted && site_ids.contains(&s.id) {
+                    s.tombstone(timestamp())
+                } else {
+                    s
+                }
+            })
             .collect::<Vec<Site>>();
 
         self.db.insert(
@@ -173,3 +515,134 @@ impl EncryptedStorage {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trash::TrashSettings;
+
+    const NAME: &str = "Jane Doe";
+    const PASSWORD: &str = "storage password";
+
+    fn storage(sites: &[Site]) -> EncryptedStorage {
+        let mut storage = EncryptedStorage {
+            db: HashMap::new(),
+            settings: HashMap::new(),
+            last_user: NAME.to_string(),
+            min_password_score: DEFAULT_MIN_PASSWORD_SCORE,
+        };
+        storage.store_sites(NAME, PASSWORD, sites);
+        storage
+    }
+
+    fn site(id: &str, site_name: &str) -> Site {
+        Site {
+            id: id.to_string(),
+            clock: 1,
+            site_name: site_name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(sites: Vec<Site>) -> Vec<String> {
+        sites.into_iter().map(|s| s.id).collect()
+    }
+
+    #[test]
+    fn formats_timestamps_natively() {
+        assert_eq!(format_timestamp(0), "unknown");
+        assert_eq!(format_timestamp(1), "1970-01-01 00:00 UTC");
+        // around the leap day of 2000
+        let leap_day = 11_016 * MS_PER_DAY;
+        assert_eq!(
+            format_timestamp(leap_day - MS_PER_DAY + (23 * 60 + 59) * 60_000),
+            "2000-02-28 23:59 UTC"
+        );
+        assert_eq!(
+            format_timestamp(leap_day + 90 * 60_000),
+            "2000-02-29 01:30 UTC"
+        );
+        assert_eq!(
+            format_timestamp(leap_day + MS_PER_DAY),
+            "2000-03-01 00:00 UTC"
+        );
+        assert_eq!(format_timestamp(1_767_225_600_000), "2026-01-01 00:00 UTC");
+    }
+
+    #[test]
+    fn trashing_and_restoring_advance_the_clock() {
+        let mut storage = storage(&[site("a", "example.com"), site("b", "other.org")]);
+        storage
+            .trash_sites(NAME, PASSWORD, &["a".to_string()])
+            .unwrap();
+        assert_eq!(ids(storage.decrypt_sites(NAME, PASSWORD).unwrap()), ["b"]);
+        let trashed = storage.decrypt_trashed_sites(NAME, PASSWORD).unwrap();
+        assert_eq!((trashed[0].id.as_str(), trashed[0].clock), ("a", 2));
+        assert!(trashed[0].trashed.is_some());
+
+        storage
+            .restore_sites(NAME, PASSWORD, &["a".to_string()])
+            .unwrap();
+        assert!(storage
+            .decrypt_trashed_sites(NAME, PASSWORD)
+            .unwrap()
+            .is_empty());
+        let all = storage.decrypt_all_sites(NAME, PASSWORD).unwrap();
+        assert_eq!((all[0].clock, all[0].trashed), (3, None));
+    }
+
+    #[test]
+    fn restoring_fails_as_a_whole_if_a_name_is_taken() {
+        let trashed = |id, name| Site {
+            trashed: Some(1),
+            ..site(id, name)
+        };
+        let mut storage = storage(&[
+            trashed("a", "example.com"),
+            trashed("b", "other.org"),
+            site("c", "example.com"),
+        ]);
+
+        let restored = storage.restore_sites(NAME, PASSWORD, &["b".to_string(), "a".to_string()]);
+        assert!(restored.is_err());
+        assert_eq!(
+            ids(storage.decrypt_trashed_sites(NAME, PASSWORD).unwrap()),
+            ["a", "b"]
+        );
+    }
+
+    #[test]
+    fn purges_sites_trashed_longer_than_the_retention() {
+        let now = timestamp();
+        let trashed = |id, days_ago| Site {
+            trashed: Some(now - days_ago * MS_PER_DAY),
+            ..site(id, id)
+        };
+        let sites = [
+            trashed("old", 8),
+            trashed("recent", 6),
+            site("live", "live"),
+        ];
+        let with_retention = |retention_days| {
+            let mut storage = storage(&sites);
+            let settings = Settings {
+                trash: TrashSettings { retention_days },
+                ..Default::default()
+            };
+            storage.store_settings(NAME, PASSWORD, &settings);
+            storage.purge_expired_trash(NAME, PASSWORD).unwrap();
+            storage.decrypt_all_sites(NAME, PASSWORD).unwrap()
+        };
+
+        let purged = with_retention(7);
+        let summary = purged
+            .iter()
+            .map(|s| (s.id.as_str(), s.deleted))
+            .collect::<Vec<_>>();
+        // purged sites leave a tombstone for synchronization
+        assert_eq!(summary, [("old", true), ("recent", false), ("live", false)]);
+
+        // without retention, trashed sites are kept
+        assert!(with_retention(0).iter().all(|s| !s.deleted));
+    }
+}
@@ -0,0 +1,166 @@
+//! Strength estimation of master passwords with zxcvbn
+//!
+//! zxcvbn splits a password into the sequence of patterns (common passwords and words, keyboard
+//! walks, sequences, repeats, dates or random characters) that is cheapest to guess. The guesses of
+//! the sequence determine a score from 0 (too guessable) to 4 (very unguessable).
+
+use leptos::*;
+use zxcvbn::zxcvbn;
+
+/// Guesses per second of an offline attack on a master password
+///
+/// The master key is derived with scrypt (N = 32768, r = 8, p = 2), which needs 128 * N * r bytes,
+/// i.e. 32 MiB of memory per guess. The rate is a rough assumption for a well-equipped attacker,
+/// not a measured figure.
+const SCRYPT_GUESSES_PER_SECOND: f64 = 1e4;
+
+/// Estimated strength of a password
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    pub guesses: f64,
+    /// 0 (too guessable) to 4 (very unguessable)
+    pub score: u8,
+    pub warning: Option<String>,
+}
+
+impl Estimate {
+    /// Expected seconds an offline attack on the scrypt derived master key takes
+    pub fn crack_seconds(&self) -> f64 {
+        self.guesses / SCRYPT_GUESSES_PER_SECOND
+    }
+
+    /// Description and bootstrap color of the score
+    pub fn rating(&self) -> (&'static str, &'static str) {
+        score_label(self.score)
+    }
+}
+
+pub fn score_label(score: u8) -> (&'static str, &'static str) {
+    match score {
+        0 => ("too guessable", "danger"),
+        1 => ("very guessable", "danger"),
+        2 => ("somewhat guessable", "warning"),
+        3 => ("safely unguessable", "info"),
+        _ => ("very unguessable", "success"),
+    }
+}
+
+/// Estimate the strength of a password, penalizing parts of the given user inputs (e.g. the name)
+pub fn estimate(password: &str, user_inputs: &[&str]) -> Estimate {
+    let user_inputs = user_inputs
+        .iter()
+        .flat_map(|input| input.split_whitespace())
+        .collect::<Vec<_>>();
+    match zxcvbn(password, &user_inputs) {
+        Ok(entropy) => Estimate {
+            guesses: entropy.guesses() as f64,
+            score: entropy.score(),
+            warning: entropy
+                .feedback()
+                .as_ref()
+                .and_then(|feedback| feedback.warning())
+                .map(|warning| warning.to_string()),
+        },
+        // zxcvbn refuses blank passwords
+        Err(_) => Estimate {
+            guesses: 0.0,
+            score: 0,
+            warning: None,
+        },
+    }
+}
+
+/// Human readable duration
+pub fn display_time(seconds: f64) -> String {
+    const MINUTE: f64 = 60.0;
+    const HOUR: f64 = 60.0 * MINUTE;
+    const DAY: f64 = 24.0 * HOUR;
+    const MONTH: f64 = 31.0 * DAY;
+    const YEAR: f64 = 12.0 * MONTH;
+    const CENTURY: f64 = 100.0 * YEAR;
+
+    let (amount, unit) = match seconds {
+        s if s < 1.0 => return "less than a second".to_string(),
+        s if s < MINUTE => (s, "second"),
+        s if s < HOUR => (s / MINUTE, "minute"),
+        s if s < DAY => (s / HOUR, "hour"),
+        s if s < MONTH => (s / DAY, "day"),
+        s if s < YEAR => (s / MONTH, "month"),
+        s if s < CENTURY => (s / YEAR, "year"),
+        _ => return "centuries".to_string(),
+    };
+    let amount = amount.round() as u64;
+    format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+/// Strength of a new master password with the estimated time to guess it
+#[component]
+pub fn StrengthMeter(
+    cx: Scope,
+    password: RwSignal<String>,
+    // user name, whose parts make a password weaker
+    name: RwSignal<String>,
+    min_score: Signal<u8>,
+) -> impl IntoView {
+    let estimate = move || estimate(&password(), &[&name()]);
+
+    view! { cx,
+        {move || {
+            let estimate = estimate();
+            let (rating, color) = estimate.rating();
+            let width = 20 * (estimate.score as u32 + 1);
+            view! { cx,
+                <div class="progress mb-1" style="height: 0.5rem">
+                    <div class=format!("progress-bar bg-{}", color) style=format!("width: {}%", width) />
+                </div>
+                <small class="text-muted">
+                    <span class=format!("text-{}", color)>{rating}</span>
+                    {format!(", about {} to guess offline", display_time(estimate.crack_seconds()))}
+                </small>
+                {estimate.warning.map(|w| view! { cx, <div><small class="text-danger">{w}</small></div> })}
+                {(estimate.score < min_score()).then(|| view! { cx,
+                    <div>
+                        <small>{format!("New identities require a password that is at least {}.", score_label(min_score()).0)}</small>
+                    </div>
+                })}
+            }
+        }}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_well_known_passwords() {
+        assert_eq!(estimate("password", &[]).score, 0);
+        assert_eq!(estimate("Tr0ub4dor&3", &[]).score, 2);
+        assert_eq!(estimate("correcthorsebatterystaple", &[]).score, 3);
+        assert!(estimate("password", &[]).warning.is_some());
+    }
+
+    #[test]
+    fn blank_passwords_are_too_guessable() {
+        assert_eq!(estimate("", &[]).score, 0);
+        assert_eq!(estimate("", &[]).crack_seconds(), 0.0);
+    }
+
+    #[test]
+    fn parts_of_the_name_weaken_a_password() {
+        let password = "fitzgeraldwinterbottom";
+        assert!(
+            estimate(password, &["Tabitha Fitzgerald Winterbottom"]).guesses
+                < estimate(password, &[]).guesses
+        );
+    }
+
+    #[test]
+    fn displays_durations() {
+        assert_eq!(display_time(0.5), "less than a second");
+        assert_eq!(display_time(1.0), "1 second");
+        assert_eq!(display_time(90.0 * 60.0), "2 hours");
+        assert_eq!(display_time(3.0 * 24.0 * 3600.0), "3 days");
+        assert_eq!(display_time(1e12), "centuries");
+    }
+}
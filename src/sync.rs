@@ -0,0 +1,290 @@
+use std::error::Error;
+
+use gloo_net::http::Request;
+use leptos::*;
+use mpw::masterkey::MasterKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    merge::merge_vaults, sites::Site, storage::EncryptedSites, webdav::WebDavSync, RwLoginData,
+    RwStorage,
+};
+
+const SYNC_ID_SITE: &str = "__sync__";
+const SYNC_ID_TYPE: &str = "Maximum";
+const SYNC_ID_COUNTER: i32 = 1;
+
+// number of upload attempts if other devices keep uploading in between
+const SYNC_ATTEMPTS: usize = 3;
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct SyncSettings {
+    /// base URL of the sync server (sync is disabled if empty)
+    #[serde(default)]
+    pub server_url: String,
+    /// revision of the vault on the server at the last successful sync
+    #[serde(default)]
+    pub revision: u64,
+}
+
+/// A vault as stored on the sync server
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct Vault {
+    revision: u64,
+    blob: EncryptedSites,
+}
+
+/// ID of the user's vault on the server
+///
+/// It is the hash of a password generated from the master key, so the server can neither
+/// recover the user name nor the master key from it.
+fn vault_id(masterkey: &MasterKey) -> String {
+    let secret = masterkey.generate_password(SYNC_ID_SITE, &SYNC_ID_TYPE.into(), SYNC_ID_COUNTER);
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+async fn fetch_vault(url: &str) -> Result<Option<Vault>, Box<dyn Error>> {
+    let response = Request::get(url).send().await?;
+    match response.status() {
+        200 => Ok(Some(response.json().await?)),
+        404 => Ok(None),
+        status => Err(format!("Unexpected response from sync server: {}", status).into()),
+    }
+}
+
+/// Upload a vault, returns false if the server holds a newer revision than expected
+async fn upload_vault(url: &str, vault: &Vault) -> Result<bool, Box<dyn Error>> {
+    let response = Request::put(url).json(vault)?.send().await?;
+    match response.status() {
+        200 => Ok(true),
+        409 => Ok(false),
+        status => Err(format!("Unexpected response from sync server: {}", status).into()),
+    }
+}
+
+fn sorted(mut sites: Vec<Site>) -> Vec<Site> {
+    sites.sort_by(|s1, s2| s1.id.cmp(&s2.id));
+    sites
+}
+
+/// Merge the local sites with a remote copy
+///
+/// Returns the merged sites and whether they differ from the remote copy (i.e. need uploading).
+pub fn merge_remote(local_sites: &[Site], remote_sites: Vec<Site>) -> (Vec<Site>, bool) {
+    let remote_sites = sorted(remote_sites);
+    let sites = sorted(merge_vaults(local_sites, &remote_sites));
+    let changed = sites != remote_sites;
+    (sites, changed)
+}
+
+/// Merge the local sites with the server's copy and upload the result
+///
+/// Returns the merged sites together with the revision they are stored under on the server.
+pub async fn sync_sites(
+    settings: &SyncSettings,
+    masterkey: &MasterKey,
+    storage_password: &str,
+    local_sites: Vec<Site>,
+) -> Result<(Vec<Site>, u64), Box<dyn Error>> {
+    let url = format!(
+        "{}/vaults/{}",
+        settings.server_url.trim_end_matches('/'),
+        vault_id(masterkey)
+    );
+
+    for _ in 0..SYNC_ATTEMPTS {
+        let (sites, revision) = match fetch_vault(&url).await? {
+            Some(remote) => {
+                let remote_sites = remote.blob.decrypt(storage_password)?;
+                let (sites, changed) = merge_remote(&local_sites, remote_sites);
+                if !changed {
+                    // nothing new to upload
+                    return Ok((sites, remote.revision));
+                }
+                (sites, remote.revision + 1)
+            }
+            None => (local_sites.clone(), 1),
+        };
+
+        let vault = Vault {
+            revision,
+            blob: EncryptedSites::from_sites(&sites, storage_password),
+        };
+        if upload_vault(&url, &vault).await? {
+            return Ok((sites, revision));
+        }
+    }
+
+    Err("Vault on the sync server keeps changing, please try again later".into())
+}
+
+#[component]
+pub fn Synchronization(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let masterkey = move || login_data().unwrap().masterkey;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // signals
+    let status = create_rw_signal(cx, "".to_string());
+    let syncing = create_rw_signal(cx, false);
+
+    // derived signals
+    let settings = move || {
+        store()
+            .decrypt_settings(&login_name(), &storage_password())
+            .unwrap_or_default()
+    };
+    let server_url = move || settings().sync.server_url;
+
+    let set_server_url = move |url: String| {
+        let mut settings = settings();
+        if settings.sync.server_url != url {
+            // a different server knows nothing about our revisions
+            settings.sync = SyncSettings {
+                server_url: url,
+                revision: 0,
+            };
+            store.update(|data| data.store_settings(&login_name(), &storage_password(), &settings));
+        }
+    };
+
+    let sync_now = move |_| {
+        if server_url().is_empty() || syncing() {
+            return;
+        }
+        syncing.set(true);
+        status.set("Synchronizing...".to_string());
+
+        spawn_local(async move {
+            let local_sites = store()
+                .decrypt_all_sites(&login_name(), &storage_password())
+                .unwrap_or(Vec::new());
+            let result = sync_sites(
+                &settings().sync,
+                &masterkey(),
+                &storage_password(),
+                local_sites,
+            )
+            .await;
+
+            match result {
+                Ok((sites, revision)) => {
+                    store.update(|data| {
+                        data.merge_synced_sites(&login_name(), &storage_password(), &sites)
+                            .unwrap_or_else(|e| log!("Error storing synchronized sites: {}", e));
+                        let mut settings = data
+                            .decrypt_settings(&login_name(), &storage_password())
+                            .unwrap_or_default();
+                        settings.sync.revision = revision;
+                        data.store_settings(&login_name(), &storage_password(), &settings);
+                    });
+                    status.set(format!("Synchronized (revision {})", revision));
+                }
+                Err(e) => status.set(format!("Synchronization failed: {}", e)),
+            }
+            syncing.set(false);
+        });
+    };
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-header text-bg-secondary text-bg-override">
+                <span class="fs-4">"Synchronization"</span>
+            </div>
+            <div class="card-body text-bg-light">
+                <p class="text-muted">
+                    "Sites are uploaded encrypted with your storage password. "
+                    "The server only sees an opaque blob and a hashed vault ID."
+                </p>
+
+                // Server URL input field
+                <div class="input-group mb-3">
+                    <span class="input-group-text">
+                        <i class="fa-solid fa-server"/>
+                    </span>
+                    <input type="text" class="form-control" placeholder="https://sync.example.com"
+                        on:change=move |ev| set_server_url(event_target_value(&ev).trim().to_string())
+                        prop:value=server_url
+                    />
+                    <button class="btn btn-secondary text-bg-override" type="button"
+                        on:click=sync_now
+                        disabled=move || server_url().is_empty() || syncing()
+                    >
+                        <i class="fa-solid fa-rotate" />" Sync now"
+                    </button>
+                </div>
+
+                <small class="text-muted">
+                    {move || format!("Last synchronized revision: {}", settings().sync.revision)}
+                </small>
+                <p>{move || status()}</p>
+            </div>
+        </div>
+
+        <WebDavSync />
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_remote_keeps_changes_from_both_sides() {
+        let shared = Site {
+            id: "shared".to_string(),
+            clock: 1,
+            site_name: "example.com".to_string(),
+            counter: 1,
+            ..Default::default()
+        };
+        let local = vec![
+            Site {
+                clock: 2,
+                counter: 2,
+                ..shared.clone()
+            },
+            Site {
+                id: "local".to_string(),
+                clock: 1,
+                site_name: "local.org".to_string(),
+                ..Default::default()
+            },
+        ];
+        let remote = vec![
+            shared.clone(),
+            Site {
+                id: "remote".to_string(),
+                clock: 1,
+                site_name: "remote.net".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let (sites, changed) = merge_remote(&local, remote.clone());
+        assert!(changed);
+        let names = sites
+            .iter()
+            .map(|s| (s.site_name.as_str(), s.counter))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![("local.org", 0), ("remote.net", 0), ("example.com", 2)]
+        );
+
+        // merging the result again changes nothing
+        let (again, changed) = merge_remote(&sites, sites.clone());
+        assert!(!changed);
+        assert_eq!(again, sites);
+        // the remote copy alone is not changed by an empty local vault
+        assert!(!merge_remote(&[], remote).1);
+    }
+}
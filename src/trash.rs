@@ -0,0 +1,246 @@
+use std::time::Duration;
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    storage::{format_timestamp, timestamp, MS_PER_DAY},
+    RwLoginData, RwStorage, RwUndo,
+};
+
+// how long deleted sites can be restored from the toast
+const UNDO_SECONDS: u64 = 10;
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TrashSettings {
+    /// days after which trashed sites are deleted permanently (0 keeps them until purged manually)
+    pub retention_days: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        TrashSettings { retention_days: 30 }
+    }
+}
+
+/// Sites that can still be restored with the undo toast
+#[derive(Clone, Debug, PartialEq)]
+pub struct Undo {
+    site_ids: Vec<String>,
+    message: String,
+    // distinguishes the toasts of consecutive deletions
+    created: u64,
+    /// restoring failed; the toast shows why until it is closed
+    failed: bool,
+}
+
+/// Move sites to the trash and offer to undo it for a few seconds
+pub fn trash_with_undo(cx: Scope, site_ids: Vec<String>, message: String) {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+    let undo = use_context::<RwUndo>(cx).unwrap().0;
+
+    let Some(login) = login_data() else {
+        return;
+    };
+    store.update(|data| {
+        data.trash_sites(&login.name, &login.storage_password, &site_ids)
+            .unwrap_or_else(|e| log!("Error moving sites to the trash: {}", e))
+    });
+
+    let created = timestamp();
+    undo.set(Some(Undo {
+        site_ids,
+        message,
+        created,
+        failed: false,
+    }));
+    set_timeout(
+        move || {
+            if undo().map_or(false, |u| u.created == created) {
+                undo.set(None);
+            }
+        },
+        Duration::from_secs(UNDO_SECONDS),
+    );
+}
+
+#[component]
+pub fn UndoToast(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+    let undo = use_context::<RwUndo>(cx).unwrap().0;
+
+    let restore = move |_| {
+        let Some(u) = undo() else {
+            return;
+        };
+        let mut restored = Ok(());
+        store.update(|data| {
+            restored = data.restore_sites(&login_name(), &storage_password(), &u.site_ids)
+        });
+        undo.set(restored.err().map(|e| Undo {
+            site_ids: Vec::new(),
+            message: format!("Could not restore the sites: {}", e),
+            created: timestamp(),
+            failed: true,
+        }));
+    };
+
+    view! { cx,
+        {move || undo().map(|u| view! { cx,
+            <div class="toast-container position-fixed bottom-0 end-0 p-3">
+                <div class=if u.failed {"toast show align-items-center text-bg-danger border-0"} else {"toast show align-items-center text-bg-secondary border-0"}>
+                    <div class="d-flex">
+                        <div class="toast-body">{u.message}</div>
+                        {(!u.failed).then(|| view! { cx,
+                            <button class="btn btn-sm btn-light me-2 m-auto" type="button" on:click=restore>
+                                <i class="fa-solid fa-rotate-left" />" Undo"
+                            </button>
+                        })}
+                        <button class="btn-close btn-close-white me-2 m-auto" type="button"
+                            on:click=move |_| undo.set(None)
+                        />
+                    </div>
+                </div>
+            </div>
+        })}
+    }
+}
+
+#[component]
+pub fn Trash(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // signals
+    let error = create_rw_signal::<Option<String>>(cx, None);
+
+    // derived signals
+    let trashed_sites = move || {
+        store()
+            .decrypt_trashed_sites(&login_name(), &storage_password())
+            .unwrap_or(Vec::new())
+    };
+    let settings = move || {
+        store()
+            .decrypt_settings(&login_name(), &storage_password())
+            .unwrap_or_default()
+    };
+    let retention_days = move || settings().trash.retention_days;
+
+    let set_retention_days = move |days: u32| {
+        let mut settings = settings();
+        settings.trash.retention_days = days;
+        store.update(|data| {
+            data.store_settings(&login_name(), &storage_password(), &settings);
+            data.purge_expired_trash(&login_name(), &storage_password())
+                .unwrap_or_else(|e| log!("Error purging the trash: {}", e));
+        });
+    };
+    let restore = move |site_ids: Vec<String>| {
+        store.update(|data| {
+            match data.restore_sites(&login_name(), &storage_password(), &site_ids) {
+                Ok(()) => error.set(None),
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+    let purge = move |site_ids: Vec<String>| {
+        store.update(|data| {
+            data.delete_sites(&login_name(), &storage_password(), &site_ids)
+                .unwrap_or_else(|e| log!("Error deleting sites: {}", e))
+        });
+    };
+    let empty_trash = move |_| {
+        let confirmed = window()
+            .confirm_with_message("Permanently delete all sites in the trash?")
+            .unwrap_or(false);
+        if confirmed {
+            purge(trashed_sites().into_iter().map(|s| s.id).collect());
+        }
+    };
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-header text-bg-secondary text-bg-override">
+                <span class="fs-4">"Trash"</span>
+            </div>
+            <div class="card-body text-bg-light">
+                <div class="row align-items-center">
+                    <div class="col-12 col-md-6 mb-2">
+                        <div class="input-group">
+                            <span class="input-group-text">"Delete permanently after"</span>
+                            <input class="form-control" type="number" min="0"
+                                on:change=move |ev| set_retention_days(event_target_value(&ev).parse().unwrap_or(0))
+                                prop:value=retention_days
+                            />
+                            <span class="input-group-text">"days"</span>
+                        </div>
+                        <small class="text-muted">"0 keeps sites in the trash until deleted manually"</small>
+                    </div>
+                    <div class="col-12 col-md-6 mb-2">
+                        <button class="btn btn-light btn-outline-danger float-end" type="button"
+                            disabled=move || trashed_sites().is_empty()
+                            on:click=empty_trash
+                        >
+                            <i class="fa-solid fa-trash-can" />" Empty trash"
+                        </button>
+                    </div>
+                </div>
+                {move || error().map(|e| view! { cx, <div class="text-danger">{e}</div> })}
+            </div>
+        </div>
+
+        <ul class="list-group mb-3">
+            {move || trashed_sites().is_empty().then(|| view! { cx,
+                <li class="list-group-item text-muted">"The trash is empty."</li>
+            })}
+            {move || trashed_sites().into_iter().map(|site| {
+                let trashed = site.trashed.unwrap_or_default();
+                let expires = (retention_days() > 0).then(|| {
+                    let expiry = trashed + retention_days() as u64 * MS_PER_DAY;
+                    let days_left = expiry.saturating_sub(timestamp()) / MS_PER_DAY;
+                    format!(", deleted permanently in {} days", days_left)
+                });
+                let restore_id = site.id.clone();
+                let purge_id = site.id.clone();
+                view! { cx,
+                    <li class="list-group-item d-flex justify-content-between align-items-center">
+                        <span>
+                            {site.display_name().to_string()}
+                            <small class="text-muted ms-2">{site.login_name.clone()}</small>
+                            <br />
+                            <small class="text-muted">
+                                "Trashed "{format_timestamp(trashed)}{expires}
+                            </small>
+                        </span>
+                        <span>
+                            <button class="btn btn-sm btn-light btn-outline-secondary me-2" type="button"
+                                on:click=move |_| restore(vec![restore_id.clone()])
+                            >
+                                <i class="fa-solid fa-rotate-left" />" Restore"
+                            </button>
+                            <button class="btn btn-sm btn-light btn-outline-danger" type="button"
+                                on:click=move |_| purge(vec![purge_id.clone()])
+                            >
+                                <i class="fa-solid fa-xmark" />" Delete forever"
+                            </button>
+                        </span>
+                    </li>
+                }
+            })
+            .collect::<Vec<_>>()}
+        </ul>
+    }
+}
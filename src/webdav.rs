@@ -0,0 +1,308 @@
+use std::error::Error;
+
+use gloo_net::http::{Request, Response};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{sites::Site, storage::EncryptedSites, sync::merge_remote, RwLoginData, RwStorage};
+
+// number of upload attempts if the file keeps changing in between
+const WEBDAV_ATTEMPTS: usize = 3;
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct WebDavSettings {
+    /// URL of the vault file on the WebDAV server (WebDAV sync is disabled if empty)
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    /// ETag of the vault file at the last successful sync
+    #[serde(default)]
+    pub etag: Option<String>,
+}
+
+impl WebDavSettings {
+    fn request(&self, request: Request) -> Result<Request, Box<dyn Error>> {
+        if self.username.is_empty() {
+            return Ok(request);
+        }
+        let credentials = web_sys::window()
+            .unwrap()
+            .btoa(&format!("{}:{}", self.username, self.password))
+            .map_err(|_| "Username and password must only contain latin-1 characters")?;
+        Ok(request.header("Authorization", &format!("Basic {}", credentials)))
+    }
+}
+
+/// Version of the vault file when it was read, which writes are conditional on
+#[derive(Clone, Debug, PartialEq)]
+enum FileVersion {
+    Missing,
+    ETag(String),
+    /// servers without ETags may still report the modification date
+    LastModified(String),
+    Unknown,
+}
+
+impl FileVersion {
+    fn of(response: &Response) -> FileVersion {
+        let headers = response.headers();
+        match (headers.get("ETag"), headers.get("Last-Modified")) {
+            (Some(etag), _) => FileVersion::ETag(etag),
+            (None, Some(date)) => FileVersion::LastModified(date),
+            (None, None) => FileVersion::Unknown,
+        }
+    }
+
+    fn etag(&self) -> Option<String> {
+        match self {
+            FileVersion::ETag(etag) => Some(etag.clone()),
+            _ => None,
+        }
+    }
+
+    /// Header that makes a write fail if the file is no longer in this version
+    fn precondition(&self) -> Result<(&'static str, String), Box<dyn Error>> {
+        match self {
+            FileVersion::Missing => Ok(("If-None-Match", "*".to_string())),
+            FileVersion::ETag(etag) => Ok(("If-Match", etag.clone())),
+            FileVersion::LastModified(date) => Ok(("If-Unmodified-Since", date.clone())),
+            FileVersion::Unknown => Err(
+                "The WebDAV server reports neither ETags nor modification dates, so changes from other devices could be overwritten"
+                    .into(),
+            ),
+        }
+    }
+}
+
+/// Read the vault file together with its version
+async fn fetch_file(
+    settings: &WebDavSettings,
+) -> Result<(Option<EncryptedSites>, FileVersion), Box<dyn Error>> {
+    let response = settings
+        .request(Request::get(&settings.url))?
+        .send()
+        .await?;
+    match response.status() {
+        200 => Ok((Some(response.json().await?), FileVersion::of(&response))),
+        404 => Ok((None, FileVersion::Missing)),
+        status => Err(format!("Unexpected response from WebDAV server: {}", status).into()),
+    }
+}
+
+/// Write the vault file unless it changed since it was read in the given version
+///
+/// Returns `None` if the file was changed (or created) in the meantime, otherwise the ETag of the
+/// written file (if the server reports one).
+async fn upload_file(
+    settings: &WebDavSettings,
+    blob: &EncryptedSites,
+    read_version: &FileVersion,
+) -> Result<Option<Option<String>>, Box<dyn Error>> {
+    let (header, value) = read_version.precondition()?;
+    let response = settings
+        .request(Request::put(&settings.url))?
+        .header(header, &value)
+        .json(blob)?
+        .send()
+        .await?;
+    match response.status() {
+        200 | 201 | 204 => Ok(Some(response.headers().get("ETag"))),
+        412 => Ok(None),
+        status => Err(format!("Unexpected response from WebDAV server: {}", status).into()),
+    }
+}
+
+/// Merge the local sites with the vault file on the WebDAV server and write back the result
+///
+/// Writes are conditional on the version of the file that was read (also when creating it), so
+/// that concurrent changes from other devices are detected and merged instead of overwritten. Returns the merged sites, the file's new ETag and
+/// whether the file was changed by another device since the last sync.
+pub async fn sync_webdav(
+    settings: &WebDavSettings,
+    storage_password: &str,
+    local_sites: Vec<Site>,
+) -> Result<(Vec<Site>, Option<String>, bool), Box<dyn Error>> {
+    for _ in 0..WEBDAV_ATTEMPTS {
+        let (remote, read_version) = fetch_file(settings).await?;
+        let read_etag = read_version.etag();
+        let remote_changed =
+            remote.is_some() && settings.etag.is_some() && read_etag != settings.etag;
+        let sites = match remote {
+            Some(blob) => {
+                let (sites, changed) = merge_remote(&local_sites, blob.decrypt(storage_password)?);
+                if !changed {
+                    // nothing new to upload
+                    return Ok((sites, read_etag, remote_changed));
+                }
+                sites
+            }
+            None => local_sites.clone(),
+        };
+
+        // another device wrote the file in the meantime (412): read and merge it again
+        let blob = EncryptedSites::from_sites(&sites, storage_password);
+        if let Some(new_etag) = upload_file(settings, &blob, &read_version).await? {
+            return Ok((sites, new_etag, remote_changed));
+        }
+    }
+
+    Err("Vault file on the WebDAV server keeps changing, please try again later".into())
+}
+
+#[component]
+pub fn WebDavSync(cx: Scope) -> impl IntoView {
+    // from global context
+    let login_data = use_context::<RwLoginData>(cx).unwrap().0;
+    let login_name = move || login_data().unwrap().name;
+    let storage_password = move || login_data().unwrap().storage_password;
+
+    let store = use_context::<RwStorage>(cx).unwrap().0;
+
+    // signals
+    let status = create_rw_signal(cx, "".to_string());
+    let syncing = create_rw_signal(cx, false);
+
+    // derived signals
+    let settings = move || {
+        store()
+            .decrypt_settings(&login_name(), &storage_password())
+            .unwrap_or_default()
+    };
+    let webdav = move || settings().webdav;
+
+    let update_webdav = move |update: &dyn Fn(&mut WebDavSettings)| {
+        let mut settings = settings();
+        update(&mut settings.webdav);
+        store.update(|data| data.store_settings(&login_name(), &storage_password(), &settings));
+    };
+
+    let sync_now = move |_| {
+        if webdav().url.is_empty() || syncing() {
+            return;
+        }
+        syncing.set(true);
+        status.set("Synchronizing...".to_string());
+
+        spawn_local(async move {
+            let local_sites = store()
+                .decrypt_all_sites(&login_name(), &storage_password())
+                .unwrap_or(Vec::new());
+            let result = sync_webdav(&webdav(), &storage_password(), local_sites).await;
+
+            match result {
+                Ok((sites, etag, remote_changed)) => {
+                    store.update(|data| {
+                        data.merge_synced_sites(&login_name(), &storage_password(), &sites)
+                            .unwrap_or_else(|e| log!("Error storing synchronized sites: {}", e));
+                        let mut settings = data
+                            .decrypt_settings(&login_name(), &storage_password())
+                            .unwrap_or_default();
+                        settings.webdav.etag = etag;
+                        data.store_settings(&login_name(), &storage_password(), &settings);
+                    });
+                    status.set(if remote_changed {
+                        "Synchronized (merged changes from other devices)".to_string()
+                    } else {
+                        "Synchronized".to_string()
+                    });
+                }
+                Err(e) => status.set(format!("Synchronization failed: {}", e)),
+            }
+            syncing.set(false);
+        });
+    };
+
+    view! { cx,
+        <div class="card mb-3 border-dark">
+            <div class="card-header text-bg-secondary text-bg-override">
+                <span class="fs-4">"WebDAV"</span>
+            </div>
+            <div class="card-body text-bg-light">
+                <p class="text-muted">
+                    "Stores your encrypted sites in a file on a WebDAV server (e.g. Nextcloud). "
+                    "The server has to allow cross-origin requests from this app."
+                </p>
+
+                // File URL input field
+                <div class="input-group mb-3">
+                    <span class="input-group-text">
+                        <i class="fa-solid fa-cloud"/>
+                    </span>
+                    <input type="text" class="form-control" placeholder="https://cloud.example.com/remote.php/dav/files/me/mpw.json"
+                        on:change=move |ev| {
+                            let url = event_target_value(&ev).trim().to_string();
+                            update_webdav(&|webdav| {
+                                if webdav.url != url {
+                                    // a different file knows nothing about our ETag
+                                    webdav.url = url.clone();
+                                    webdav.etag = None;
+                                }
+                            });
+                        }
+                        prop:value=move || webdav().url
+                    />
+                </div>
+
+                // Credentials input fields
+                <div class="input-group mb-3">
+                    <span class="input-group-text">
+                        <i class="fa-solid fa-user"/>
+                    </span>
+                    <input type="text" class="form-control" placeholder="Username"
+                        on:change=move |ev| {
+                            let username = event_target_value(&ev);
+                            update_webdav(&|webdav| webdav.username = username.clone());
+                        }
+                        prop:value=move || webdav().username
+                    />
+                    <span class="input-group-text">
+                        <i class="fa-solid fa-key"/>
+                    </span>
+                    <input type="password" class="form-control" placeholder="App Password"
+                        on:change=move |ev| {
+                            let password = event_target_value(&ev);
+                            update_webdav(&|webdav| webdav.password = password.clone());
+                        }
+                        prop:value=move || webdav().password
+                    />
+                    <button class="btn btn-secondary text-bg-override" type="button"
+                        on:click=sync_now
+                        disabled=move || webdav().url.is_empty() || syncing()
+                    >
+                        <i class="fa-solid fa-rotate" />" Sync now"
+                    </button>
+                </div>
+
+                <p>{move || status()}</p>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_are_conditional_on_the_read_version() {
+        let header = |version: FileVersion| version.precondition().unwrap();
+        assert_eq!(
+            header(FileVersion::Missing),
+            ("If-None-Match", "*".to_string())
+        );
+        assert_eq!(
+            header(FileVersion::ETag("\"abc\"".to_string())),
+            ("If-Match", "\"abc\"".to_string())
+        );
+        let date = "Wed, 21 Oct 2015 07:28:00 GMT".to_string();
+        assert_eq!(
+            header(FileVersion::LastModified(date.clone())),
+            ("If-Unmodified-Since", date)
+        );
+        // never write unconditionally
+        assert!(FileVersion::Unknown.precondition().is_err());
+    }
+}
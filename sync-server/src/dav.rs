@@ -0,0 +1,182 @@
+//! Minimal WebDAV stand-in for testing WebDAV synchronization locally
+//!
+//! Files below `/dav/` can be read with `GET` and written with `PUT`, honoring ETags in
+//! `If-Match` and `If-None-Match` for conditional writes.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+
+use crate::{read_body, response, State};
+
+pub(crate) const DAV_PREFIX: &str = "/dav/";
+
+fn dav_path(state: &State, name: &str) -> PathBuf {
+    state.data_dir.join("dav").join(name)
+}
+
+/// Entity tag of a WebDAV file's content
+fn etag(content: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// WebDAV file names are restricted to a single path segment
+fn valid_dav_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !name.starts_with('.')
+}
+
+fn with_etag(mut response: Response<Body>, etag: String) -> Response<Body> {
+    response
+        .headers_mut()
+        .insert(header::ETAG, etag.parse().unwrap());
+    response
+}
+
+async fn get_dav(state: &State, name: &str) -> Response<Body> {
+    match tokio::fs::read(dav_path(state, name)).await {
+        Ok(content) => with_etag(
+            response(StatusCode::OK, Body::from(content.clone())),
+            etag(&content),
+        ),
+        Err(_) => response(StatusCode::NOT_FOUND, Body::empty()),
+    }
+}
+
+/// Write a WebDAV file if the preconditions given by `If-Match`/`If-None-Match` hold
+async fn put_dav(state: &State, name: &str, req: Request<Body>) -> Response<Body> {
+    let header_value = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|v: &header::HeaderValue| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let if_match = header_value(header::IF_MATCH);
+    let if_none_match = header_value(header::IF_NONE_MATCH);
+    let content = match read_body(req.into_body()).await {
+        Ok(content) => content,
+        Err(status) => return response(status, Body::empty()),
+    };
+
+    let _guard = state.write_lock.lock().await;
+    let path = dav_path(state, name);
+    let current_etag = tokio::fs::read(&path).await.ok().map(|c| etag(&c));
+    let precondition_failed = match (&if_match, &if_none_match, &current_etag) {
+        (Some(expected), _, Some(current)) => expected != "*" && expected != current,
+        (Some(_), _, None) => true,
+        (None, Some(_), Some(_)) => true,
+        _ => false,
+    };
+    if precondition_failed {
+        return response(StatusCode::PRECONDITION_FAILED, Body::empty());
+    }
+
+    let written = async {
+        tokio::fs::create_dir_all(state.data_dir.join("dav")).await?;
+        tokio::fs::write(&path, &content).await
+    };
+    match written.await {
+        Ok(()) => {
+            let status = if current_etag.is_some() {
+                StatusCode::NO_CONTENT
+            } else {
+                StatusCode::CREATED
+            };
+            with_etag(response(status, Body::empty()), etag(&content))
+        }
+        Err(e) => {
+            eprintln!("Error writing WebDAV file: {}", e);
+            response(StatusCode::INTERNAL_SERVER_ERROR, Body::empty())
+        }
+    }
+}
+
+pub(crate) async fn handle_dav(state: Arc<State>, req: Request<Body>) -> Response<Body> {
+    let name = req.uri().path()[DAV_PREFIX.len()..].to_string();
+    if !valid_dav_name(&name) {
+        return response(StatusCode::BAD_REQUEST, Body::empty());
+    }
+
+    match *req.method() {
+        Method::OPTIONS => response(StatusCode::NO_CONTENT, Body::empty()),
+        Method::GET => get_dav(&state, &name).await,
+        Method::PUT => put_dav(&state, &name, req).await,
+        _ => response(StatusCode::METHOD_NOT_ALLOWED, Body::empty()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{send, state};
+
+    const PATH: &str = "/dav/vault.json";
+
+    async fn put(
+        state: &Arc<State>,
+        header: Option<(header::HeaderName, &str)>,
+        body: &str,
+    ) -> Response<Body> {
+        let mut req = Request::builder().method(Method::PUT).uri(PATH);
+        if let Some((name, value)) = header {
+            req = req.header(name, value);
+        }
+        handle_dav(
+            state.clone(),
+            req.body(Body::from(body.to_string())).unwrap(),
+        )
+        .await
+    }
+
+    fn response_etag(response: &Response<Body>) -> String {
+        response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn conditional_writes() {
+        let state = state();
+
+        let created = put(&state, Some((header::IF_NONE_MATCH, "*")), "first").await;
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let first_etag = response_etag(&created);
+
+        // the file exists now, so creating it again fails
+        let exists = put(&state, Some((header::IF_NONE_MATCH, "*")), "other").await;
+        assert_eq!(exists.status(), StatusCode::PRECONDITION_FAILED);
+
+        let read = send(&state, Method::GET, PATH, Body::empty()).await;
+        assert_eq!(response_etag(&read), first_etag);
+
+        let updated = put(&state, Some((header::IF_MATCH, &first_etag)), "second").await;
+        assert_eq!(updated.status(), StatusCode::NO_CONTENT);
+        assert_ne!(response_etag(&updated), first_etag);
+
+        // a write based on the outdated ETag is rejected
+        let outdated = put(&state, Some((header::IF_MATCH, &first_etag)), "third").await;
+        assert_eq!(outdated.status(), StatusCode::PRECONDITION_FAILED);
+
+        let read = send(&state, Method::GET, PATH, Body::empty()).await;
+        let content = hyper::body::to_bytes(read.into_body()).await.unwrap();
+        assert_eq!(&content[..], b"second");
+    }
+
+    #[tokio::test]
+    async fn rejects_nested_paths() {
+        let state = state();
+        let nested = send(&state, Method::GET, "/dav/../vault.json", Body::empty()).await;
+        assert_eq!(nested.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,308 @@
+//! Minimal sync server for the masterpassword web app
+//!
+//! The server stores one opaque blob per vault together with a revision number. Vaults are
+//! addressed by an ID that the client derives by hashing a secret, so the server never learns
+//! user names, site names or keys; the blobs are encrypted before they leave the browser.
+//!
+//! With the `webdav-stand-in` feature, the server additionally serves a minimal WebDAV stand-in
+//! for testing WebDAV synchronization locally (see the `dav` module).
+//!
+//! Configuration via environment variables:
+//! - `MPW_SYNC_ADDR`: address to listen on (default `127.0.0.1:8421`)
+//! - `MPW_SYNC_DATA`: directory for the stored vaults (default `sync-data`)
+
+#[cfg(feature = "webdav-stand-in")]
+mod dav;
+
+use std::{convert::Infallible, env, net::SocketAddr, path::PathBuf, sync::Arc};
+
+use hyper::{
+    body::HttpBody,
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8421";
+const DEFAULT_DATA_DIR: &str = "sync-data";
+const VAULT_PREFIX: &str = "/vaults/";
+
+/// Largest accepted request body (vaults of thousands of sites stay far below)
+const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// A stored vault: the encrypted sites of one user and their revision
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct Vault {
+    revision: u64,
+    blob: String,
+}
+
+pub(crate) struct State {
+    pub(crate) data_dir: PathBuf,
+    // serializes the read-compare-write cycle of uploads
+    pub(crate) write_lock: Mutex<()>,
+}
+
+impl State {
+    fn vault_path(&self, id: &str) -> PathBuf {
+        self.data_dir.join(format!("{}.json", id))
+    }
+
+    async fn load(&self, id: &str) -> Option<Vault> {
+        let content = tokio::fs::read(self.vault_path(id)).await.ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    async fn store(&self, id: &str, vault: &Vault) -> std::io::Result<()> {
+        // write to a temporary file first so that a crash never leaves a truncated vault behind
+        let path = self.vault_path(id);
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, serde_json::to_vec(vault)?).await?;
+        tokio::fs::rename(tmp_path, path).await
+    }
+}
+
+/// Vault IDs are hex-encoded SHA-256 hashes
+fn valid_id(id: &str) -> bool {
+    id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub(crate) fn response(status: StatusCode, body: Body) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        // the web app is served from a different origin
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, PUT, OPTIONS")
+        .header(
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            "Authorization, Content-Type, If-Match, If-None-Match",
+        )
+        .header(header::ACCESS_CONTROL_EXPOSE_HEADERS, "ETag")
+        .body(body)
+        .unwrap()
+}
+
+/// Read a request body of at most `MAX_BODY_BYTES`
+pub(crate) async fn read_body(mut body: Body) -> Result<Vec<u8>, StatusCode> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn json_response<T: Serialize>(status: StatusCode, value: &T) -> Response<Body> {
+    response(status, Body::from(serde_json::to_vec(value).unwrap()))
+}
+
+/// Upload a new revision of a vault
+///
+/// The upload is only accepted if its revision directly follows the stored one. Otherwise the
+/// client missed changes from another device and gets the current vault back to merge with.
+async fn put_vault(state: &State, id: &str, body: Body) -> Response<Body> {
+    let bytes = match read_body(body).await {
+        Ok(bytes) => bytes,
+        Err(status) => return response(status, Body::empty()),
+    };
+    let Ok(vault) = serde_json::from_slice::<Vault>(&bytes) else {
+        return response(StatusCode::BAD_REQUEST, Body::empty());
+    };
+
+    let _guard = state.write_lock.lock().await;
+    let current = state.load(id).await;
+    let expected = current.as_ref().map(|v| v.revision + 1).unwrap_or(1);
+    if vault.revision != expected {
+        return match current {
+            Some(current) => json_response(StatusCode::CONFLICT, &current),
+            None => response(StatusCode::CONFLICT, Body::empty()),
+        };
+    }
+
+    match state.store(id, &vault).await {
+        Ok(()) => json_response(StatusCode::OK, &vault.revision),
+        Err(e) => {
+            eprintln!("Error storing vault: {}", e);
+            response(StatusCode::INTERNAL_SERVER_ERROR, Body::empty())
+        }
+    }
+}
+
+async fn handle(state: Arc<State>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    #[cfg(feature = "webdav-stand-in")]
+    if req.uri().path().starts_with(dav::DAV_PREFIX) {
+        return Ok(dav::handle_dav(state, req).await);
+    }
+
+    let Some(id) = req.uri().path().strip_prefix(VAULT_PREFIX).map(str::to_string) else {
+        return Ok(response(StatusCode::NOT_FOUND, Body::empty()));
+    };
+    if !valid_id(&id) {
+        return Ok(response(StatusCode::BAD_REQUEST, Body::empty()));
+    }
+
+    let res = match *req.method() {
+        Method::OPTIONS => response(StatusCode::NO_CONTENT, Body::empty()),
+        Method::GET => match state.load(&id).await {
+            Some(vault) => json_response(StatusCode::OK, &vault),
+            None => response(StatusCode::NOT_FOUND, Body::empty()),
+        },
+        Method::PUT => put_vault(&state, &id, req.into_body()).await,
+        _ => response(StatusCode::METHOD_NOT_ALLOWED, Body::empty()),
+    };
+    Ok(res)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let addr: SocketAddr = env::var("MPW_SYNC_ADDR")
+        .unwrap_or(DEFAULT_ADDR.to_string())
+        .parse()?;
+    let data_dir = PathBuf::from(env::var("MPW_SYNC_DATA").unwrap_or(DEFAULT_DATA_DIR.to_string()));
+    tokio::fs::create_dir_all(&data_dir).await?;
+
+    let state = Arc::new(State {
+        data_dir,
+        write_lock: Mutex::new(()),
+    });
+
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+    });
+
+    println!("Listening on http://{}", addr);
+    Server::bind(&addr).serve(make_service).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const ID: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    /// Server state with an empty data directory of its own
+    pub(crate) fn state() -> Arc<State> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let data_dir = env::temp_dir().join(format!(
+            "mpw-sync-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        std::fs::create_dir_all(&data_dir).unwrap();
+        Arc::new(State {
+            data_dir,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    pub(crate) async fn send(
+        state: &Arc<State>,
+        method: Method,
+        path: &str,
+        body: Body,
+    ) -> Response<Body> {
+        let req = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(body)
+            .unwrap();
+        handle(state.clone(), req).await.unwrap()
+    }
+
+    async fn put_revision(state: &Arc<State>, revision: u64, blob: &str) -> Response<Body> {
+        let vault = Vault {
+            revision,
+            blob: blob.to_string(),
+        };
+        let body = Body::from(serde_json::to_vec(&vault).unwrap());
+        send(state, Method::PUT, &format!("{}{}", VAULT_PREFIX, ID), body).await
+    }
+
+    async fn body_vault(response: Response<Body>) -> Vault {
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn vault_round_trip() {
+        let state = state();
+        let path = format!("{}{}", VAULT_PREFIX, ID);
+
+        let missing = send(&state, Method::GET, &path, Body::empty()).await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+        assert_eq!(
+            put_revision(&state, 1, "first").await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            put_revision(&state, 2, "second").await.status(),
+            StatusCode::OK
+        );
+
+        let stored = send(&state, Method::GET, &path, Body::empty()).await;
+        assert_eq!(stored.status(), StatusCode::OK);
+        let vault = body_vault(stored).await;
+        assert_eq!(vault.revision, 2);
+        assert_eq!(vault.blob, "second");
+    }
+
+    #[tokio::test]
+    async fn outdated_revision_conflicts_with_current_vault() {
+        let state = state();
+        assert_eq!(
+            put_revision(&state, 1, "mine").await.status(),
+            StatusCode::OK
+        );
+
+        // another device uploads revision 1 without having seen ours
+        let conflict = put_revision(&state, 1, "theirs").await;
+        assert_eq!(conflict.status(), StatusCode::CONFLICT);
+        let current = body_vault(conflict).await;
+        assert_eq!((current.revision, current.blob.as_str()), (1, "mine"));
+
+        // skipping a revision is rejected as well
+        assert_eq!(
+            put_revision(&state, 3, "theirs").await.status(),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            put_revision(&state, 2, "merged").await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn first_upload_must_be_revision_one() {
+        let state = state();
+        let conflict = put_revision(&state, 2, "blob").await;
+        assert_eq!(conflict.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_ids_and_bodies() {
+        let state = state();
+        let invalid_id = send(&state, Method::GET, "/vaults/../secret", Body::empty()).await;
+        assert_eq!(invalid_id.status(), StatusCode::BAD_REQUEST);
+
+        let path = format!("{}{}", VAULT_PREFIX, ID);
+        let invalid_body = send(&state, Method::PUT, &path, Body::from("not json")).await;
+        assert_eq!(invalid_body.status(), StatusCode::BAD_REQUEST);
+
+        let oversized = Body::from(vec![b' '; MAX_BODY_BYTES + 1]);
+        let too_large = send(&state, Method::PUT, &path, oversized).await;
+        assert_eq!(too_large.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}